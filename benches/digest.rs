@@ -1,16 +1,19 @@
 use halo2_base::halo2_proofs::{
     circuit::{AssignedCell, Cell, Layouter, Region, SimpleFloorPlanner, Value},
+    dev::MockProver,
     halo2curves::bn256::{Bn256, Fr},
     plonk::{
         create_proof,
         keygen_pk,
         keygen_vk,
         Circuit,
+        Column,
         ConstraintSystem,
         Error,
         // create_proof, keygen_pk, keygen_vk, Advice, Circuit, Column, ConstraintSystem, Error,
         Expression,
         Fixed,
+        Instance,
         Selector,
         TableColumn,
         VirtualCells,
@@ -51,7 +54,9 @@ use std::marker::PhantomData;
 use criterion::{criterion_group, criterion_main, Criterion};
 
 use halo2_base::{gates::range::RangeStrategy::Vertical, SKIP_FIRST_PASS};
-use halo2_dynamic_sha256::Sha256DynamicConfig;
+use halo2_dynamic_sha256::{
+    constrain_cells_to_instance, DigestStats, Sha256DynamicConfig, SpreadTable,
+};
 
 const K: u32 = 17;
 
@@ -132,23 +137,7 @@ fn bench(name: &str, k: u32, c: &mut Criterion) {
         const LOOKUP_BITS: usize = 16;
     }
 
-    // Initialize the polynomial commitment parameters
-    let params_name = format!("./benches/sha256_params_{}", k);
-    let params_path = Path::new(&params_name);
-    if File::open(&params_path).is_err() {
-        let params: ParamsKZG<Bn256> = ParamsKZG::new(k);
-        let mut buf = Vec::new();
-
-        params.write(&mut buf).expect("Failed to write params");
-        let mut file = File::create(&params_path).expect("Failed to create sha256_params");
-
-        file.write_all(&buf[..])
-            .expect("Failed to write params to file");
-    }
-
-    let params_fs = File::open(&params_path).expect("couldn't load sha256_params");
-    let params: ParamsKZG<Bn256> =
-        ParamsKZG::read::<_>(&mut BufReader::new(params_fs)).expect("Failed to read params");
+    let params = read_or_create_params(k);
 
     let test_inputs = vec![vec![0x1; 56], vec![0u8, 0u8, 0u8]];
     let circuit = BenchCircuit {
@@ -209,10 +198,244 @@ fn bench(name: &str, k: u32, c: &mut Criterion) {
     // });
 }
 
+// Initialize the polynomial commitment parameters
+fn read_or_create_params(k: u32) -> ParamsKZG<Bn256> {
+    let params_name = format!("./benches/sha256_params_{}", k);
+    let params_path = Path::new(&params_name);
+    if File::open(&params_path).is_err() {
+        let params: ParamsKZG<Bn256> = ParamsKZG::new(k);
+        let mut buf = Vec::new();
+
+        params.write(&mut buf).expect("Failed to write params");
+        let mut file = File::create(&params_path).expect("Failed to create sha256_params");
+
+        file.write_all(&buf[..])
+            .expect("Failed to write params to file");
+    }
+
+    let params_fs = File::open(&params_path).expect("couldn't load sha256_params");
+    ParamsKZG::read::<_>(&mut BufReader::new(params_fs)).expect("Failed to read params")
+}
+
 fn criterion_benchmark(c: &mut Criterion) {
     bench("sha256", K, c);
     // bench("sha256", 20, c);
 }
 
-criterion_group!(benches, criterion_benchmark);
+// Hashes `input` with one `digest` call and records its stats. The spread table is computed
+// by `load`, or taken from `spread_table` when one is shared. With `COMPOSITE`, each spread
+// lookup checks two 4-bit limbs and `NUM_BITS_LOOKUP` must be 4.
+struct DigestStatsCircuit<const NUM_BITS_LOOKUP: usize, const COMPOSITE: bool> {
+    input: Vec<u8>,
+    trust_input_bytes: bool,
+    spread_table: Option<SpreadTable<Fr>>,
+    stats: std::cell::Cell<Option<DigestStats>>,
+}
+
+impl<const NUM_BITS_LOOKUP: usize, const COMPOSITE: bool>
+    DigestStatsCircuit<NUM_BITS_LOOKUP, COMPOSITE>
+{
+    const MAX_BYTE_SIZE: usize = 128;
+    const NUM_ADVICE: usize = 9;
+    const NUM_FIXED: usize = 1;
+    const NUM_LOOKUP_ADVICE: usize = 1;
+    const LOOKUP_BITS: usize = 16;
+    const NUM_SPREAD_COLUMNS: usize = 2;
+
+    fn new(input: &[u8], trust_input_bytes: bool) -> Self {
+        Self {
+            input: input.to_vec(),
+            trust_input_bytes,
+            spread_table: None,
+            stats: std::cell::Cell::new(None),
+        }
+    }
+
+    fn with_spread_table(mut self, spread_table: &SpreadTable<Fr>) -> Self {
+        self.spread_table = Some(spread_table.clone());
+        self
+    }
+
+    // Runs the circuit with `MockProver` and returns the stats of its digest.
+    fn mock_stats(&self) -> DigestStats {
+        let public = Sha256::digest(&self.input)
+            .iter()
+            .map(|byte| Fr::from(*byte as u64))
+            .collect();
+        let prover = MockProver::run(K, self, vec![public]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+        self.stats.get().unwrap()
+    }
+}
+
+impl<const NUM_BITS_LOOKUP: usize, const COMPOSITE: bool> Circuit<Fr>
+    for DigestStatsCircuit<NUM_BITS_LOOKUP, COMPOSITE>
+{
+    type Config = (Sha256DynamicConfig<Fr>, Column<Instance>);
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self {
+            spread_table: self.spread_table.clone(),
+            ..Self::new(&[], self.trust_input_bytes)
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        let range_config = RangeConfig::configure(
+            meta,
+            Vertical,
+            &[Self::NUM_ADVICE],
+            &[Self::NUM_LOOKUP_ADVICE],
+            Self::NUM_FIXED,
+            Self::LOOKUP_BITS,
+            0,
+            K as usize,
+        );
+        let instance = meta.instance_column();
+        meta.enable_equality(instance);
+        let sha256 = if COMPOSITE {
+            Sha256DynamicConfig::configure_with_composite_spread(
+                meta,
+                vec![Self::MAX_BYTE_SIZE],
+                range_config,
+                Self::NUM_SPREAD_COLUMNS,
+                true,
+            )
+        } else {
+            Sha256DynamicConfig::configure(
+                meta,
+                vec![Self::MAX_BYTE_SIZE],
+                range_config,
+                NUM_BITS_LOOKUP,
+                Self::NUM_SPREAD_COLUMNS,
+                true,
+            )
+        };
+        (sha256, instance)
+    }
+
+    fn synthesize(
+        &self,
+        (config, instance): Self::Config,
+        mut layouter: impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        let mut sha256 = config.clone();
+        sha256.trust_input_bytes(self.trust_input_bytes);
+        let range = sha256.range().clone();
+        range.load_lookup_table(&mut layouter)?;
+        match self.spread_table.as_ref() {
+            Some(spread_table) => sha256.load_with_spread_table(&mut layouter, spread_table)?,
+            None => sha256.load(&mut layouter)?,
+        }
+        let mut first_pass = SKIP_FIRST_PASS;
+        let mut output_cells = vec![];
+        layouter.assign_region(
+            || "sha256 digest stats",
+            |region| {
+                if first_pass {
+                    first_pass = false;
+                    return Ok(());
+                }
+                let ctx = &mut sha256.new_context(region);
+                let (result, stats) = sha256.digest_with_stats(ctx, &self.input, None)?;
+                self.stats.set(Some(stats));
+                output_cells = result.output_bytes.iter().map(|v| v.cell()).collect();
+                range.finalize(ctx);
+                Ok(())
+            },
+        )?;
+        constrain_cells_to_instance(&mut layouter, &output_cells, instance, 0)
+    }
+}
+
+// Cells saved by `trust_input_bytes`, and the synthesis time with and without the input
+// range checks.
+fn bench_trust_input_bytes(c: &mut Criterion) {
+    let mut group = c.benchmark_group("SHA256: input range checks");
+    group.sample_size(10);
+    let input = vec![0x5a; 100];
+    let checked = DigestStatsCircuit::<8, false>::new(&input, false);
+    let trusted = DigestStatsCircuit::<8, false>::new(&input, true);
+    let (checked_stats, trusted_stats) = (checked.mock_stats(), trusted.mock_stats());
+    println!(
+        "input range checks: {} advice cells, {} range lookup cells",
+        checked_stats.advice_cells - trusted_stats.advice_cells,
+        checked_stats.range_lookup_cells - trusted_stats.range_lookup_cells
+    );
+    group.bench_function("checked", |b| b.iter(|| checked.mock_stats()));
+    group.bench_function("trusted", |b| b.iter(|| trusted.mock_stats()));
+    group.finish();
+}
+
+// Spread lookup rows and cells of one digest with 4-bit limbs, 8-bit limbs and pairs of 4-bit
+// limbs against the composite table, and their synthesis times.
+fn bench_spread_limbs(c: &mut Criterion) {
+    let mut group = c.benchmark_group("SHA256: spread limbs");
+    group.sample_size(10);
+    let input = vec![0x5a; 100];
+    let report = |name: &str, table_rows: usize, stats: DigestStats| {
+        let num_columns = DigestStatsCircuit::<8, false>::NUM_SPREAD_COLUMNS;
+        println!(
+            "{}: {} table rows, {} spread lookups in {} rows, {} advice cells",
+            name,
+            table_rows,
+            stats.spread_lookups,
+            (stats.spread_lookups + num_columns - 1) / num_columns,
+            stats.advice_cells
+        );
+    };
+    let limbs_4 = DigestStatsCircuit::<4, false>::new(&input, false);
+    let limbs_8 = DigestStatsCircuit::<8, false>::new(&input, false);
+    let composite = DigestStatsCircuit::<4, true>::new(&input, false);
+    report("4-bit limbs", 1 << 4, limbs_4.mock_stats());
+    report("8-bit limbs", 1 << 8, limbs_8.mock_stats());
+    report("composite 4-bit limb pairs", 1 << 8, composite.mock_stats());
+    group.bench_function("4-bit limbs", |b| b.iter(|| limbs_4.mock_stats()));
+    group.bench_function("8-bit limbs", |b| b.iter(|| limbs_8.mock_stats()));
+    group.bench_function("composite", |b| b.iter(|| composite.mock_stats()));
+    group.finish();
+}
+
+// keygen_vk and MockProver runs for 8 circuits, each computing its spread table with `load`
+// vs loading one precomputed with `load_with_spread_table`.
+fn bench_spread_table(c: &mut Criterion) {
+    const NUM_CIRCUITS: usize = 8;
+    let params = read_or_create_params(K);
+    let input = vec![0x5a; 100];
+    let table = SpreadTable::<Fr>::new(8);
+    let fresh = (0..NUM_CIRCUITS)
+        .map(|_| DigestStatsCircuit::<8, false>::new(&input, false))
+        .collect::<Vec<_>>();
+    let shared = (0..NUM_CIRCUITS)
+        .map(|_| DigestStatsCircuit::<8, false>::new(&input, false).with_spread_table(&table))
+        .collect::<Vec<_>>();
+    let mut group = c.benchmark_group("SHA256: spread table for 8 circuits");
+    group.sample_size(10);
+    for (name, circuits) in [("load", &fresh), ("load_with_spread_table", &shared)] {
+        group.bench_function(format!("{}-keygen_vk", name), |b| {
+            b.iter(|| {
+                for circuit in circuits.iter() {
+                    keygen_vk(&params, circuit).expect("keygen_vk should not fail");
+                }
+            })
+        });
+        group.bench_function(format!("{}-mock_prover", name), |b| {
+            b.iter(|| {
+                for circuit in circuits.iter() {
+                    circuit.mock_stats();
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    criterion_benchmark,
+    bench_spread_table,
+    bench_trust_input_bytes,
+    bench_spread_limbs
+);
 criterion_main!(benches);
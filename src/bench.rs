@@ -0,0 +1,140 @@
+use crate::{constrain_cells_to_instance, num_blocks_for_len, Sha256DynamicConfig};
+use halo2_base::gates::range::{RangeConfig, RangeStrategy::Vertical};
+use halo2_base::halo2_proofs::{
+    circuit::{Layouter, SimpleFloorPlanner},
+    dev::{MockProver, VerifyFailure},
+    halo2curves::bn256::Fr,
+    plonk::{Circuit, Column, ConstraintSystem, Error, Instance},
+};
+use halo2_base::SKIP_FIRST_PASS;
+use sha2::{Digest, Sha256};
+
+const NUM_ADVICE: usize = 8;
+const NUM_FIXED: usize = 1;
+const NUM_LOOKUP_ADVICE: usize = 4;
+const LOOKUP_BITS: usize = 16;
+const NUM_BITS_LOOKUP: usize = 8;
+const NUM_SPREAD_COLUMNS: usize = 4;
+
+/// A circuit of `2^K` rows hashing each message with its own `digest` call and exposing the
+/// digests in one instance column, for benchmarking message-size distributions with
+/// `criterion`. Build it with `build_sha_circuit`.
+#[derive(Debug, Clone)]
+pub struct Sha256BenchCircuit<const K: u32> {
+    msgs: Vec<Vec<u8>>,
+    max_byte_sizes: Vec<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Sha256BenchConfig {
+    sha256: Sha256DynamicConfig<Fr>,
+    instance: Column<Instance>,
+}
+
+/// Sizes a circuit for `msgs`, giving each message the fewest blocks that fit it.
+/// Fails with `Error::NotEnoughRowsAvailable` if `2^K` rows cannot hold the range lookup table.
+pub fn build_sha_circuit<const K: u32>(msgs: &[Vec<u8>]) -> Result<Sha256BenchCircuit<K>, Error> {
+    if K as usize <= LOOKUP_BITS {
+        return Err(Error::NotEnoughRowsAvailable { current_k: K });
+    }
+    let max_byte_sizes = msgs
+        .iter()
+        .map(|msg| num_blocks_for_len(msg.len()) * 64)
+        .collect();
+    Ok(Sha256BenchCircuit {
+        msgs: msgs.to_vec(),
+        max_byte_sizes,
+    })
+}
+
+impl<const K: u32> Sha256BenchCircuit<K> {
+    /// The public inputs: the digest bytes of every message, in order.
+    pub fn instances(&self) -> Vec<Vec<Fr>> {
+        let digests = self
+            .msgs
+            .iter()
+            .flat_map(|msg| Sha256::digest(msg).to_vec())
+            .map(|byte| Fr::from(byte as u64))
+            .collect();
+        vec![digests]
+    }
+
+    /// Synthesizes the circuit with `MockProver` and returns every failed constraint.
+    /// Panics if the circuit cannot be synthesized.
+    pub fn mock_prove(&self) -> Result<(), Vec<VerifyFailure>> {
+        let prover =
+            MockProver::run(K, self, self.instances()).expect("MockProver::run should not fail");
+        prover.verify()
+    }
+}
+
+impl<const K: u32> Circuit<Fr> for Sha256BenchCircuit<K> {
+    type Config = Sha256BenchConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self {
+            msgs: vec![],
+            max_byte_sizes: self.max_byte_sizes.clone(),
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        let range = RangeConfig::configure(
+            meta,
+            Vertical,
+            &[NUM_ADVICE],
+            &[NUM_LOOKUP_ADVICE],
+            NUM_FIXED,
+            LOOKUP_BITS,
+            0,
+            K as usize,
+        );
+        let instance = meta.instance_column();
+        meta.enable_equality(instance);
+        let sha256 = Sha256DynamicConfig::configure(
+            meta,
+            vec![],
+            range,
+            NUM_BITS_LOOKUP,
+            NUM_SPREAD_COLUMNS,
+            true,
+        );
+        Sha256BenchConfig { sha256, instance }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        let mut sha256 = config.sha256.clone();
+        sha256.max_variable_byte_sizes = self.max_byte_sizes.clone();
+        let range = sha256.range().clone();
+        range.load_lookup_table(&mut layouter)?;
+        sha256.load(&mut layouter)?;
+        let mut first_pass = SKIP_FIRST_PASS;
+        let mut digest_cells = vec![];
+        layouter.assign_region(
+            || "sha256 bench",
+            |region| {
+                if first_pass {
+                    first_pass = false;
+                    return Ok(());
+                }
+                let ctx = &mut sha256.new_context(region);
+                digest_cells.clear();
+                // the layout depends only on `max_byte_sizes`, so a circuit without witnesses
+                // hashes empty messages in their place.
+                for idx in 0..self.max_byte_sizes.len() {
+                    let msg = self.msgs.get(idx).map_or(&[][..], |msg| &msg[..]);
+                    let result = sha256.digest(ctx, msg, None)?;
+                    digest_cells.extend(result.output_bytes.iter().map(|v| v.cell()));
+                }
+                range.finalize(ctx);
+                Ok(())
+            },
+        )?;
+        constrain_cells_to_instance(&mut layouter, &digest_cells, config.instance, 0)
+    }
+}
@@ -0,0 +1,119 @@
+use crate::{compose_be_bytes, state_to_be_bytes, Sha256DynamicConfig, INIT_STATE};
+use halo2_base::halo2_proofs::{circuit::Value, plonk::Error};
+use halo2_base::{
+    gates::{flex_gate::FlexGateConfig, GateInstructions, RangeInstructions},
+    utils::PrimeField,
+    AssignedValue, Context,
+};
+use itertools::Itertools;
+use std::convert::TryFrom;
+
+/// One 512-bit message block as 64 assigned bytes.
+#[derive(Debug, Clone)]
+pub struct Block<'a, F: PrimeField>(Vec<AssignedValue<'a, F>>);
+
+impl<'a, F: PrimeField> Block<'a, F> {
+    pub const NUM_BYTES: usize = 64;
+
+    /// Panics unless `bytes` holds exactly 64 bytes.
+    pub fn new(bytes: Vec<AssignedValue<'a, F>>) -> Self {
+        assert_eq!(bytes.len(), Self::NUM_BYTES);
+        Self(bytes)
+    }
+
+    /// The block `left || right` of two 32-byte halves, e.g. the children of a Merkle node.
+    pub fn from_halves(left: &[AssignedValue<'a, F>], right: &[AssignedValue<'a, F>]) -> Self {
+        assert_eq!(left.len(), Self::NUM_BYTES / 2);
+        assert_eq!(right.len(), Self::NUM_BYTES / 2);
+        Self([left, right].concat())
+    }
+
+    pub fn bytes(&self) -> &[AssignedValue<'a, F>] {
+        &self.0
+    }
+
+    /// The 16 big-endian message words of the block.
+    pub fn words(
+        &self,
+        ctx: &mut Context<'a, F>,
+        gate: &FlexGateConfig<F>,
+    ) -> Vec<AssignedValue<'a, F>> {
+        self.0
+            .chunks(4)
+            .map(|word_bytes| compose_be_bytes(ctx, gate, word_bytes))
+            .collect()
+    }
+}
+
+impl<'a, F: PrimeField> TryFrom<&[AssignedValue<'a, F>]> for Block<'a, F> {
+    type Error = Error;
+
+    fn try_from(bytes: &[AssignedValue<'a, F>]) -> Result<Self, Self::Error> {
+        if bytes.len() != Self::NUM_BYTES {
+            return Err(Error::Synthesis);
+        }
+        Ok(Self(bytes.to_vec()))
+    }
+}
+
+/// How the bytes of a message block are assigned by `digest_blocks`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockVisibility {
+    /// Fixed in the circuit as constants, e.g. a known document header. Changing such a block
+    /// changes the verifying key.
+    Public,
+    /// Range-checked advice bytes.
+    Private,
+}
+
+impl<F: PrimeField> Sha256DynamicConfig<F> {
+    /// Hashes the message made of the 64-byte `blocks`, assigning each one as constants or as
+    /// private witnesses according to its visibility. Public blocks cost no advice cells or
+    /// range checks beyond the compression itself. The message length is a whole number of
+    /// blocks, so the padding block is constant and no entry of `max_variable_byte_sizes` is
+    /// consumed. Returns the assigned blocks and the digest bytes.
+    pub fn digest_blocks<'b>(
+        &mut self,
+        ctx: &mut Context<'b, F>,
+        blocks: &[([u8; 64], BlockVisibility)],
+    ) -> Result<(Vec<Block<'b, F>>, Vec<AssignedValue<'b, F>>), Error> {
+        let range = self.range().clone();
+        let gate = range.gate();
+        let assigned_blocks = blocks
+            .iter()
+            .map(|(bytes, visibility)| {
+                let assigned = bytes
+                    .iter()
+                    .map(|byte| match visibility {
+                        BlockVisibility::Public => gate.load_constant(ctx, F::from(*byte as u64)),
+                        BlockVisibility::Private => {
+                            let assigned =
+                                gate.load_witness(ctx, Value::known(F::from(*byte as u64)));
+                            range.range_check(ctx, &assigned, 8);
+                            assigned
+                        }
+                    })
+                    .collect_vec();
+                Block::new(assigned)
+            })
+            .collect_vec();
+        let mut padding = [0u8; Block::<F>::NUM_BYTES];
+        padding[0] = 0x80;
+        padding[56..]
+            .copy_from_slice(&(8 * (Block::<F>::NUM_BYTES * blocks.len()) as u64).to_be_bytes());
+        let padding_block = Block::new(
+            padding
+                .iter()
+                .map(|byte| gate.load_constant(ctx, F::from(*byte as u64)))
+                .collect(),
+        );
+        let mut state = INIT_STATE
+            .iter()
+            .map(|h| gate.load_constant(ctx, F::from(*h as u64)))
+            .collect_vec();
+        for block in assigned_blocks.iter().chain(Some(&padding_block)) {
+            state = self.compress_block(ctx, &state, block.bytes())?;
+        }
+        Ok((assigned_blocks, state_to_be_bytes(ctx, &range, state)))
+    }
+}
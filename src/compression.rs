@@ -33,6 +33,110 @@ pub fn sha256_compression<'a, 'b: 'a, F: PrimeField>(
     assigned_input_bytes: &[AssignedValue<'a, F>],
     pre_state_words: &[AssignedValue<'a, F>],
 ) -> Result<Vec<AssignedValue<'a, F>>, Error> {
+    let (next_state_words, _) = sha256_compression_with_schedule(
+        ctx,
+        range,
+        spread_config,
+        assigned_input_bytes,
+        pre_state_words,
+    )?;
+    Ok(next_state_words)
+}
+
+/// Same as [`sha256_compression`], additionally returning the 64 message schedule words.
+pub fn sha256_compression_with_schedule<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    range: &RangeConfig<F>,
+    spread_config: &mut SpreadConfig<F>,
+    assigned_input_bytes: &[AssignedValue<'a, F>],
+    pre_state_words: &[AssignedValue<'a, F>],
+) -> Result<(Vec<AssignedValue<'a, F>>, Vec<AssignedValue<'a, F>>), Error> {
+    sha256_compression_with_round_constants(
+        ctx,
+        range,
+        spread_config,
+        assigned_input_bytes,
+        pre_state_words,
+        &ROUND_CONSTANTS,
+    )
+}
+
+/// Same as [`sha256_compression_with_schedule`], but adds `round_constants` instead of the
+/// standard `ROUND_CONSTANTS` in each round, for tweaked variants of the compression function.
+pub fn sha256_compression_with_round_constants<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    range: &RangeConfig<F>,
+    spread_config: &mut SpreadConfig<F>,
+    assigned_input_bytes: &[AssignedValue<'a, F>],
+    pre_state_words: &[AssignedValue<'a, F>],
+    round_constants: &[u32; NUM_ROUND],
+) -> Result<(Vec<AssignedValue<'a, F>>, Vec<AssignedValue<'a, F>>), Error> {
+    let (working_words, message_u32s) = sha256_compression_rounds(
+        ctx,
+        range,
+        spread_config,
+        assigned_input_bytes,
+        pre_state_words,
+        round_constants,
+    )?;
+    let next_state_words = sha256_feed_forward(ctx, range, &working_words, pre_state_words);
+    Ok((next_state_words, message_u32s))
+}
+
+/// Runs the 64 rounds and returns the working variables `a..h` *before* they are added to
+/// `pre_state_words`. This is not the SHA-256 compression function: the output is trivially
+/// invertible, so it must not be used as a hash on its own. Apply [`sha256_feed_forward`] to
+/// obtain the standard chaining value.
+pub fn sha256_compression_no_feed_forward<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    range: &RangeConfig<F>,
+    spread_config: &mut SpreadConfig<F>,
+    assigned_input_bytes: &[AssignedValue<'a, F>],
+    pre_state_words: &[AssignedValue<'a, F>],
+) -> Result<Vec<AssignedValue<'a, F>>, Error> {
+    let (working_words, _) = sha256_compression_rounds(
+        ctx,
+        range,
+        spread_config,
+        assigned_input_bytes,
+        pre_state_words,
+        &ROUND_CONSTANTS,
+    )?;
+    Ok(working_words)
+}
+
+/// Adds the working variables to the previous state word-wise modulo 2^32.
+pub fn sha256_feed_forward<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    range: &RangeConfig<F>,
+    working_words: &[AssignedValue<'a, F>],
+    pre_state_words: &[AssignedValue<'a, F>],
+) -> Vec<AssignedValue<'a, F>> {
+    let gate = range.gate();
+    working_words
+        .iter()
+        .zip(pre_state_words.iter())
+        .map(|(x, y)| {
+            let add = gate.add(ctx, QuantumCell::Existing(&x), QuantumCell::Existing(&y));
+            // println!(
+            //     "pre {:?} new {:?} add {:?}",
+            //     y.value(),
+            //     x.value(),
+            //     add.value()
+            // );
+            mod_u32(ctx, range, &add)
+        })
+        .collect_vec()
+}
+
+fn sha256_compression_rounds<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    range: &RangeConfig<F>,
+    spread_config: &mut SpreadConfig<F>,
+    assigned_input_bytes: &[AssignedValue<'a, F>],
+    pre_state_words: &[AssignedValue<'a, F>],
+    round_constants: &[u32; NUM_ROUND],
+) -> Result<(Vec<AssignedValue<'a, F>>, Vec<AssignedValue<'a, F>>), Error> {
     debug_assert_eq!(assigned_input_bytes.len(), 64);
     debug_assert_eq!(pre_state_words.len(), 8);
     let gate = range.gate();
@@ -158,7 +262,7 @@ pub fn sha256_compression<'a, 'b: 'a, F: PrimeField>(
             let add3 = gate.add(
                 ctx,
                 QuantumCell::Existing(&add2),
-                QuantumCell::Constant(F::from(ROUND_CONSTANTS[idx] as u64)),
+                QuantumCell::Constant(F::from(round_constants[idx] as u64)),
             );
             let add4 = gate.add(
                 ctx,
@@ -205,21 +309,7 @@ pub fn sha256_compression<'a, 'b: 'a, F: PrimeField>(
         a_spread = state_to_spread_u32(ctx, range, spread_config, &a)?;
     }
     let new_states = vec![a, b, c, d, e, f, g, h];
-    let next_state_words = new_states
-        .iter()
-        .zip(pre_state_words.iter())
-        .map(|(x, y)| {
-            let add = gate.add(ctx, QuantumCell::Existing(&x), QuantumCell::Existing(&y));
-            // println!(
-            //     "pre {:?} new {:?} add {:?}",
-            //     y.value(),
-            //     x.value(),
-            //     add.value()
-            // );
-            mod_u32(ctx, range, &add)
-        })
-        .collect_vec();
-    Ok(next_state_words)
+    Ok((new_states, message_u32s))
 }
 
 fn state_to_spread_u32<'a, 'b: 'a, F: PrimeField>(
@@ -273,23 +363,38 @@ fn state_to_spread_u32<'a, 'b: 'a, F: PrimeField>(
 //     sum
 // }
 
+// the largest sum reduced by `mod_u32` is T1, which adds five 32-bit words.
+const MAX_CARRY_BITS: usize = 3;
+
 fn mod_u32<'a, 'b: 'a, F: FieldExt>(
     ctx: &mut Context<'b, F>,
     range: &RangeConfig<F>,
     x: &AssignedValue<'a, F>,
 ) -> AssignedValue<'a, F> {
-    let gate = range.gate();
-    let lo = x
-        .value()
-        .map(|v| v.get_lower_32())
-        .map(|v| F::from(v as u64));
     let hi = x
         .value()
         .map(|v| (v.get_lower_128() >> 32) & ((1u128 << 32) - 1))
         .map(|v| F::from(v as u64));
+    mod_u32_with_carry(ctx, range, x, hi)
+}
+
+/// Reduces `x` modulo 2^32 given the witness `hi` for the carry `x >> 32`. Both the result and
+/// the carry are range checked, so a wrong carry cannot yield another in-range result.
+pub(crate) fn mod_u32_with_carry<'a, 'b: 'a, F: FieldExt>(
+    ctx: &mut Context<'b, F>,
+    range: &RangeConfig<F>,
+    x: &AssignedValue<'a, F>,
+    hi: Value<F>,
+) -> AssignedValue<'a, F> {
+    let gate = range.gate();
+    let lo = x
+        .value()
+        .zip(hi)
+        .map(|(x, hi)| *x - hi * F::from(1u64 << 32));
     let assigned_lo = gate.load_witness(ctx, lo);
     let assigned_hi = gate.load_witness(ctx, hi);
     range.range_check(ctx, &assigned_lo, 32);
+    range.range_check(ctx, &assigned_hi, MAX_CARRY_BITS);
     let composed = gate.mul_add(
         ctx,
         QuantumCell::Existing(&assigned_hi),
@@ -1029,9 +1134,70 @@ fn sigma_generic<'a, 'b: 'a, F: PrimeField>(
 //     )
 // }
 
+/// Off-circuit reference compression of one 16-word block, including the feed-forward.
+pub fn compress_block(state: [u32; NUM_STATE_WORD], block: [u32; 16]) -> [u32; NUM_STATE_WORD] {
+    compress_block_with_round_constants(state, block, &ROUND_CONSTANTS)
+}
+
+/// Same as [`compress_block`] with custom round constants.
+pub fn compress_block_with_round_constants(
+    state: [u32; NUM_STATE_WORD],
+    block: [u32; 16],
+    round_constants: &[u32; NUM_ROUND],
+) -> [u32; NUM_STATE_WORD] {
+    let mut w = [0u32; NUM_ROUND];
+    w[..16].copy_from_slice(&block);
+    for idx in 16..NUM_ROUND {
+        let s0 = w[idx - 15].rotate_right(7) ^ w[idx - 15].rotate_right(18) ^ (w[idx - 15] >> 3);
+        let s1 = w[idx - 2].rotate_right(17) ^ w[idx - 2].rotate_right(19) ^ (w[idx - 2] >> 10);
+        w[idx] = w[idx - 16]
+            .wrapping_add(s0)
+            .wrapping_add(w[idx - 7])
+            .wrapping_add(s1);
+    }
+    let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = state;
+    for idx in 0..NUM_ROUND {
+        let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+        let ch = (e & f) ^ (!e & g);
+        let t1 = h
+            .wrapping_add(s1)
+            .wrapping_add(ch)
+            .wrapping_add(round_constants[idx])
+            .wrapping_add(w[idx]);
+        let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+        let maj = (a & b) ^ (a & c) ^ (b & c);
+        let t2 = s0.wrapping_add(maj);
+        h = g;
+        g = f;
+        f = e;
+        e = d.wrapping_add(t1);
+        d = c;
+        c = b;
+        b = a;
+        a = t1.wrapping_add(t2);
+    }
+    let mut next_state = state;
+    for (word, round_word) in next_state.iter_mut().zip([a, b, c, d, e, f, g, h].iter()) {
+        *word = word.wrapping_add(*round_word);
+    }
+    next_state
+}
+
+/// The chaining state after compressing a fixed 64-byte first block from `INIT_STATE`, e.g. an
+/// HMAC `key ^ ipad` block. Pass it to `Sha256DynamicConfig::digest_with_iv` with
+/// `precomputed_input_len = 64` to skip that block in the circuit.
+pub fn midstate(first_block: &[u8]) -> [u32; NUM_STATE_WORD] {
+    assert_eq!(first_block.len(), 64);
+    let mut block = [0u32; 16];
+    for (word, bytes) in block.iter_mut().zip(first_block.chunks(4)) {
+        *word = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    }
+    compress_block(INIT_STATE, block)
+}
+
 pub const NUM_ROUND: usize = 64;
 pub const NUM_STATE_WORD: usize = 8;
-const ROUND_CONSTANTS: [u32; NUM_ROUND] = [
+pub const ROUND_CONSTANTS: [u32; NUM_ROUND] = [
     0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
     0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
     0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
@@ -0,0 +1,178 @@
+use crate::Sha256DynamicConfig;
+use halo2_base::halo2_proofs::{
+    circuit::{Layouter, Value},
+    plonk::{Advice, Column, ConstraintSystem, Error, Selector, TableColumn},
+    poly::Rotation,
+};
+use halo2_base::{utils::PrimeField, AssignedValue, Context};
+use itertools::Itertools;
+use std::convert::TryInto;
+use std::marker::PhantomData;
+
+/// A fixed table of allowed digests, each as its `(hi, lo)` pair of 16-byte big-endian halves,
+/// and the advice columns looked up in it.
+#[derive(Debug, Clone)]
+pub(crate) struct DigestSetConfig<F: PrimeField> {
+    q_lookup: Selector,
+    hi: Column<Advice>,
+    lo: Column<Advice>,
+    table_tag: TableColumn,
+    table_hi: TableColumn,
+    table_lo: TableColumn,
+    num_lookups: usize,
+    _f: PhantomData<F>,
+}
+
+impl<F: PrimeField> DigestSetConfig<F> {
+    pub fn configure(meta: &mut ConstraintSystem<F>) -> Self {
+        let q_lookup = meta.complex_selector();
+        let hi = meta.advice_column();
+        let lo = meta.advice_column();
+        meta.enable_equality(hi);
+        meta.enable_equality(lo);
+        let table_tag = meta.lookup_table_column();
+        let table_hi = meta.lookup_table_column();
+        let table_lo = meta.lookup_table_column();
+        // unselected rows look up (0, 0, 0), the padding row of the table. Allowed digests are
+        // tagged 1, so a selected row cannot match the padding row even for the zero digest.
+        meta.lookup("digest set lookup", |meta| {
+            let q = meta.query_selector(q_lookup);
+            let hi = meta.query_advice(hi, Rotation::cur());
+            let lo = meta.query_advice(lo, Rotation::cur());
+            vec![
+                (q.clone(), table_tag),
+                (q.clone() * hi, table_hi),
+                (q * lo, table_lo),
+            ]
+        });
+        Self {
+            q_lookup,
+            hi,
+            lo,
+            table_tag,
+            table_hi,
+            table_lo,
+            num_lookups: 0,
+            _f: PhantomData,
+        }
+    }
+
+    /// The rows of the table for `allowed`: the padding row and one row per distinct digest.
+    pub fn table_size(allowed: &[[u8; 32]]) -> usize {
+        allowed.iter().unique().count() + 1
+    }
+
+    pub fn load(&self, layouter: &mut impl Layouter<F>, allowed: &[[u8; 32]]) -> Result<(), Error> {
+        layouter.assign_table(
+            || "digest set table",
+            |mut table_region| {
+                let padding = (F::zero(), F::zero(), F::zero());
+                let rows = allowed.iter().unique().map(|digest| {
+                    let (hi, lo) = digest_halves::<F>(digest);
+                    (F::one(), hi, lo)
+                });
+                for (idx, (tag, hi, lo)) in std::iter::once(padding).chain(rows).enumerate() {
+                    let cells = [
+                        (self.table_tag, tag),
+                        (self.table_hi, hi),
+                        (self.table_lo, lo),
+                    ];
+                    for (column, value) in cells.iter() {
+                        table_region.assign_cell(
+                            || format!("digest set table at {}", idx),
+                            *column,
+                            idx,
+                            || Value::known(*value),
+                        )?;
+                    }
+                }
+                Ok(())
+            },
+        )
+    }
+
+    /// Copies `hi` and `lo` to the next free row of the lookup columns and selects it.
+    pub fn assert_in_set<'v>(
+        &mut self,
+        ctx: &mut Context<'v, F>,
+        hi: &AssignedValue<F>,
+        lo: &AssignedValue<F>,
+    ) -> Result<(), Error> {
+        let row_offset = self.num_lookups;
+        self.q_lookup.enable(&mut ctx.region, row_offset)?;
+        for (column, value) in [(self.hi, hi), (self.lo, lo)].iter() {
+            let assigned_cell = ctx.region.assign_advice(
+                || format!("digest set lookup at {}", row_offset),
+                *column,
+                row_offset,
+                || value.value,
+            )?;
+            ctx.region
+                .constrain_equal(assigned_cell.cell(), value.cell())?;
+        }
+        self.num_lookups += 1;
+        Ok(())
+    }
+
+    /// Restarts lookups at the first row, e.g. for a new region.
+    pub fn reset(&mut self) {
+        self.num_lookups = 0;
+    }
+}
+
+// The big-endian integers of bytes `0..16` and `16..32`, as `digest_to_field_pair_poseidon_ready`
+// computes them in the circuit.
+fn digest_halves<F: PrimeField>(digest: &[u8; 32]) -> (F, F) {
+    let hi = u128::from_be_bytes(digest[0..16].try_into().unwrap());
+    let lo = u128::from_be_bytes(digest[16..32].try_into().unwrap());
+    (F::from_u128(hi), F::from_u128(lo))
+}
+
+impl<F: PrimeField> Sha256DynamicConfig<F> {
+    /// Adds a fixed lookup table of allowed digests, filled by `load_digest_set`, for
+    /// `assert_digest_in_set`.
+    pub fn configure_digest_set(&mut self, meta: &mut ConstraintSystem<F>) {
+        self.digest_set = Some(DigestSetConfig::configure(meta));
+    }
+
+    /// Assigns the `allowed` digests to the table added by `configure_digest_set`.
+    /// Fails with `Error::NotEnoughRowsAvailable` if they do not fit in the usable rows.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `configure_digest_set` was not called.
+    pub fn load_digest_set(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        allowed: &[[u8; 32]],
+    ) -> Result<(), Error> {
+        let digest_set = self
+            .digest_set
+            .as_ref()
+            .expect("call configure_digest_set before loading a digest set");
+        if DigestSetConfig::<F>::table_size(allowed) > self.range.gate.max_rows {
+            return Err(Error::NotEnoughRowsAvailable {
+                current_k: Self::range_k(&self.range),
+            });
+        }
+        digest_set.load(layouter, allowed)
+    }
+
+    /// Constrains a 32-byte digest to be one of the digests loaded by `load_digest_set`, with one
+    /// lookup of its `(hi, lo)` pair, so the cost does not depend on the size of the set.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `configure_digest_set` was not called.
+    pub fn assert_digest_in_set<'b>(
+        &mut self,
+        ctx: &mut Context<'b, F>,
+        output_bytes: &[AssignedValue<'b, F>],
+    ) -> Result<(), Error> {
+        let (hi, lo) = self.digest_to_field_pair_poseidon_ready(ctx, output_bytes);
+        self.digest_set
+            .as_mut()
+            .expect("call configure_digest_set before asserting digest set membership")
+            .assert_in_set(ctx, &hi, &lo)
+    }
+}
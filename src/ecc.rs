@@ -0,0 +1,59 @@
+use crate::compose_be_bytes;
+use halo2_base::halo2_proofs::circuit::Value;
+use halo2_base::{
+    gates::{flex_gate::FlexGateConfig, GateInstructions, RangeInstructions},
+    utils::PrimeField,
+    AssignedValue, Context,
+};
+use halo2_ecc::bigint::{CRTInteger, OverflowInteger};
+use halo2_ecc::fields::{fp::FpConfig, FieldChip};
+use itertools::Itertools;
+use num_bigint::BigInt;
+
+/// Interprets the big-endian digest bytes as an integer in halo2-ecc's CRT representation,
+/// with `num_limbs` little-endian limbs of `limb_bits` bits. `limb_bits` must be a multiple of
+/// 8 so that limbs are byte aligned. The result is not reduced modulo any field.
+pub fn digest_to_crt_integer<'b, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    gate: &FlexGateConfig<F>,
+    output_bytes: &[AssignedValue<'b, F>],
+    limb_bits: usize,
+    num_limbs: usize,
+) -> CRTInteger<'b, F> {
+    assert_eq!(output_bytes.len(), 32);
+    assert_eq!(limb_bits % 8, 0);
+    let limb_bytes = limb_bits / 8;
+    assert!(limb_bytes * num_limbs >= output_bytes.len());
+    let mut limbs = output_bytes
+        .rchunks(limb_bytes)
+        .map(|limb_be_bytes| compose_be_bytes(ctx, gate, limb_be_bytes))
+        .collect_vec();
+    while limbs.len() < num_limbs {
+        limbs.push(gate.load_zero(ctx));
+    }
+    let native = compose_be_bytes(ctx, gate, output_bytes);
+    let value = output_bytes
+        .iter()
+        .fold(Value::known(BigInt::from(0u32)), |acc, byte| {
+            acc.zip(byte.value().map(|v| BigInt::from(v.get_lower_32())))
+                .map(|(acc, byte)| acc * 256 + byte)
+        });
+    CRTInteger::construct(OverflowInteger::construct(limbs, limb_bits), native, value)
+}
+
+/// Converts a digest into a properly reduced element of `Fp`, e.g. the secp256k1 scalar field
+/// for the message hash of an ECDSA verification with `halo2-ecc`.
+pub fn digest_to_field_element<'b, F: PrimeField, Fp: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    fp_chip: &FpConfig<F, Fp>,
+    output_bytes: &[AssignedValue<'b, F>],
+) -> CRTInteger<'b, F> {
+    let integer = digest_to_crt_integer(
+        ctx,
+        fp_chip.range.gate(),
+        output_bytes,
+        fp_chip.limb_bits,
+        fp_chip.num_limbs,
+    );
+    fp_chip.carry_mod(ctx, &integer)
+}
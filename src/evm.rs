@@ -0,0 +1,39 @@
+use halo2_base::utils::{fe_to_biguint, PrimeField};
+use std::convert::TryInto;
+
+/// Off-circuit counterpart of `Sha256DynamicConfig::digest_to_field_pair_poseidon_ready`:
+/// the big-endian integers of digest bytes `0..16` and `16..32`.
+pub fn digest_to_hi_lo<F: PrimeField>(digest: &[u8; 32]) -> (F, F) {
+    let hi = u128::from_be_bytes(digest[0..16].try_into().unwrap());
+    let lo = u128::from_be_bytes(digest[16..32].try_into().unwrap());
+    (F::from_u128(hi), F::from_u128(lo))
+}
+
+/// Encodes each instance as an EVM `uint256` word, i.e. 32 big-endian bytes, which is how
+/// snark-verifier lays out public inputs in the verifier calldata.
+pub fn instances_to_evm_words<F: PrimeField>(instances: &[F]) -> Vec<[u8; 32]> {
+    instances
+        .iter()
+        .map(|instance| {
+            let be_bytes = fe_to_biguint(instance).to_bytes_be();
+            let mut word = [0u8; 32];
+            word[(32 - be_bytes.len())..].copy_from_slice(&be_bytes);
+            word
+        })
+        .collect()
+}
+
+/// Recovers the digest from the `uint256` words of its `(hi, lo)` instances.
+pub fn evm_words_to_digest(hi: &[u8; 32], lo: &[u8; 32]) -> [u8; 32] {
+    assert!(
+        hi[0..16]
+            .iter()
+            .chain(lo[0..16].iter())
+            .all(|byte| *byte == 0),
+        "hi and lo must be below 2^128"
+    );
+    let mut digest = [0u8; 32];
+    digest[0..16].copy_from_slice(&hi[16..32]);
+    digest[16..32].copy_from_slice(&lo[16..32]);
+    digest
+}
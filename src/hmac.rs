@@ -0,0 +1,102 @@
+use crate::Sha256DynamicConfig;
+use halo2_base::halo2_proofs::plonk::Error;
+use halo2_base::QuantumCell;
+use halo2_base::{gates::GateInstructions, utils::PrimeField, AssignedValue, Context};
+use itertools::Itertools;
+
+/// A hash chip over assigned bytes, so constructions such as HMAC can be written once for
+/// every hash variant.
+pub trait HashChip<F: PrimeField> {
+    /// Digest size in bytes.
+    fn output_len(&self) -> usize;
+
+    /// Input block size in bytes.
+    fn block_size(&self) -> usize;
+
+    /// Hashes `input`, which must already be constrained to bytes, and returns the digest bytes.
+    /// On `Sha256DynamicConfig` the inherent `digest` over native bytes shadows this one, so call
+    /// it as `HashChip::digest` there.
+    fn digest<'b>(
+        &mut self,
+        ctx: &mut Context<'b, F>,
+        input: &[AssignedValue<'b, F>],
+    ) -> Result<Vec<AssignedValue<'b, F>>, Error>;
+}
+
+impl<F: PrimeField> HashChip<F> for Sha256DynamicConfig<F> {
+    fn output_len(&self) -> usize {
+        32
+    }
+
+    fn block_size(&self) -> usize {
+        Self::ONE_ROUND_INPUT_BYTES
+    }
+
+    fn digest<'b>(
+        &mut self,
+        ctx: &mut Context<'b, F>,
+        input: &[AssignedValue<'b, F>],
+    ) -> Result<Vec<AssignedValue<'b, F>>, Error> {
+        Ok(self.digest_assigned_bytes(ctx, input)?.output_bytes)
+    }
+}
+
+const IPAD: u8 = 0x36;
+const OPAD: u8 = 0x5c;
+
+/// Computes `HMAC(key, msg) = H((key ^ opad) || H((key ^ ipad) || msg))` (RFC 2104).
+/// `key` and `msg` must be range-checked bytes, and `key` must not be longer than a block;
+/// longer keys have to be hashed by the caller first. `gate` computes the key XORs. With the
+/// dynamic SHA-256 chip this consumes two entries of `max_variable_byte_sizes`, for the inner
+/// and the outer hash.
+pub fn hmac<'b, F: PrimeField, H: HashChip<F>, G: GateInstructions<F>>(
+    chip: &mut H,
+    ctx: &mut Context<'b, F>,
+    gate: &G,
+    key: &[AssignedValue<'b, F>],
+    msg: &[AssignedValue<'b, F>],
+) -> Result<Vec<AssignedValue<'b, F>>, Error> {
+    let block_size = chip.block_size();
+    assert!(key.len() <= block_size);
+    let zero = gate.load_zero(ctx);
+    let mut padded_key = key.to_vec();
+    padded_key.resize(block_size, zero);
+    let inner_key = padded_key
+        .iter()
+        .map(|byte| xor_const_byte(ctx, gate, byte, IPAD))
+        .collect_vec();
+    let outer_key = padded_key
+        .iter()
+        .map(|byte| xor_const_byte(ctx, gate, byte, OPAD))
+        .collect_vec();
+    let inner = chip.digest(ctx, &[inner_key, msg.to_vec()].concat())?;
+    chip.digest(ctx, &[outer_key, inner].concat())
+}
+
+fn xor_const_byte<'b, F: PrimeField, G: GateInstructions<F>>(
+    ctx: &mut Context<'b, F>,
+    gate: &G,
+    byte: &AssignedValue<'b, F>,
+    constant: u8,
+) -> AssignedValue<'b, F> {
+    let bits = gate.num_to_bits(ctx, byte, 8);
+    let mut sum = gate.load_zero(ctx);
+    for (idx, bit) in bits.iter().enumerate() {
+        let xored = if (constant >> idx) & 1 == 1 {
+            gate.sub(
+                ctx,
+                QuantumCell::Constant(F::one()),
+                QuantumCell::Existing(bit),
+            )
+        } else {
+            bit.clone()
+        };
+        sum = gate.mul_add(
+            ctx,
+            QuantumCell::Existing(&xored),
+            QuantumCell::Constant(F::from(1u64 << idx)),
+            QuantumCell::Existing(&sum),
+        );
+    }
+    sum
+}
@@ -1,19 +1,41 @@
+#[cfg(any(test, feature = "bench"))]
+pub mod bench;
+mod block;
 mod compression;
+mod digest_set;
+mod ecc;
+mod evm;
+mod hmac;
+mod merkle;
+mod nullifier;
+mod padding;
+#[cfg(feature = "prover")]
+pub mod prover;
+mod rlp;
 pub(crate) mod spread;
 pub(crate) mod utils;
+pub use block::*;
 pub use compression::*;
+pub use ecc::*;
+pub use evm::*;
+pub use hmac::*;
+pub use nullifier::*;
+pub use padding::*;
+pub use rlp::*;
+pub use spread::{SpreadDecomposition, SpreadTable};
 // pub use eth_types::Field;
 // pub use zkevm_circuits::sha256_circuit::{
 //     sha256_compression::{Sha256AssignedRows, Sha256CompressionConfig},
 //     util::H,
 // };
 
+use digest_set::DigestSetConfig;
 use generic_array::GenericArray;
 use halo2_base::halo2_proofs::{
     circuit::{AssignedCell, Cell, Layouter, Region, SimpleFloorPlanner, Value},
     plonk::{
-        Advice, Circuit, Column, ConstraintSystem, Error, Expression, Fixed, Selector, TableColumn,
-        VirtualCells,
+        Advice, Circuit, Column, ConstraintSystem, Error, Expression, Fixed, Instance, Selector,
+        TableColumn, VirtualCells,
     },
     poly::Rotation,
 };
@@ -29,6 +51,8 @@ use hex;
 use itertools::Itertools;
 use sha2::{compress256, Digest, Sha256};
 use spread::SpreadConfig;
+use std::convert::TryInto;
+use std::ops::Range;
 
 // const Sha256BitChipRowPerRound: usize = 72;
 // const BLOCK_BYTE: usize = 64;
@@ -37,8 +61,72 @@ use spread::SpreadConfig;
 #[derive(Debug, Clone)]
 pub struct AssignedHashResult<'a, F: PrimeField> {
     pub input_len: AssignedValue<'a, F>,
+    pub input_bit_len: AssignedValue<'a, F>,
     pub input_bytes: Vec<AssignedValue<'a, F>>,
     pub output_bytes: Vec<AssignedValue<'a, F>>,
+    /// The 8 big-endian digest words `H0..H7`, whose byte decomposition is `output_bytes`.
+    pub output_words: Vec<AssignedValue<'a, F>>,
+    /// The 64 message schedule words `W[0..64]` of every compressed block, for debugging.
+    #[cfg(feature = "debug-schedule")]
+    pub message_schedules: Vec<Vec<AssignedValue<'a, F>>>,
+}
+
+impl<'a, F: PrimeField> AssignedHashResult<'a, F> {
+    /// The 32 digest byte cells in order, without collecting them.
+    pub fn bytes_iter(&self) -> impl Iterator<Item = &AssignedValue<'a, F>> + '_ {
+        self.output_bytes.iter()
+    }
+
+    /// Debug helper: checks the digest witnesses against `sha2` for `input` and panics listing
+    /// every mismatching byte. Catches witness generation bugs before `MockProver::verify`.
+    /// Does nothing when the witnesses are unknown, e.g. during keygen.
+    #[cfg(debug_assertions)]
+    pub fn assert_digest(&self, input: &[u8]) {
+        let expected = Sha256::digest(input);
+        let mut mismatches = vec![];
+        for (idx, (assigned, byte)) in self.output_bytes.iter().zip(expected.iter()).enumerate() {
+            assigned.value().map(|v| {
+                let witness = v.get_lower_32();
+                if witness != *byte as u32 {
+                    mismatches.push(format!(
+                        "byte {}: witness {:#04x}, expected {:#04x}",
+                        idx, witness, byte
+                    ));
+                }
+            });
+        }
+        assert!(
+            mismatches.is_empty(),
+            "digest witness mismatch for a {}-byte input:\n{}",
+            input.len(),
+            mismatches.join("\n")
+        );
+    }
+}
+
+/// Resources consumed by one `digest` call, for catching constraint regressions.
+/// Copy constraints are not tracked by `Context`, so they are not reported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DigestStats {
+    /// Cells assigned in the flex gate advice columns.
+    pub advice_cells: usize,
+    /// Cells queued for the range check lookup.
+    pub range_lookup_cells: usize,
+    /// Lookups into the spread table, one per limb or, with a composite spread table, one per
+    /// pair of limbs.
+    pub spread_lookups: usize,
+}
+
+/// The parameters that determine the shape of a `Sha256DynamicConfig` circuit, and thus its
+/// verifying key, e.g. as a `HashMap` key for caching keys.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Sha256ConfigParams {
+    /// The circuit size is `2^k` rows.
+    pub k: u32,
+    pub max_variable_byte_sizes: Vec<usize>,
+    pub num_bits_lookup: usize,
+    pub num_spread_columns: usize,
+    pub is_input_range_check: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -48,10 +136,21 @@ pub struct Sha256DynamicConfig<F: PrimeField> {
     spread_config: SpreadConfig<F>,
     pub cur_hash_idx: usize,
     is_input_range_check: bool,
+    is_spread_table_loaded: std::cell::Cell<bool>,
+    round_constants: [u32; NUM_ROUND],
+    digest_set: Option<DigestSetConfig<F>>,
 }
 
 impl<F: PrimeField> Sha256DynamicConfig<F> {
     const ONE_ROUND_INPUT_BYTES: usize = 64;
+
+    /// Configures the spread lookup with `2^num_bits_lookup`-row tables and
+    /// `num_advice_columns` pairs of dense and spread advice columns.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the spread table does not fit in the usable rows of `range`; use
+    /// `check_spread_table_size` to get an error instead.
     pub fn configure(
         meta: &mut ConstraintSystem<F>,
         max_variable_byte_sizes: Vec<usize>,
@@ -64,6 +163,7 @@ impl<F: PrimeField> Sha256DynamicConfig<F> {
             debug_assert_eq!(byte % Self::ONE_ROUND_INPUT_BYTES, 0);
         }
         // let max_byte_sum = max_byte_sizes.iter().sum::<usize>();
+        Self::assert_spread_table_size(&range, 1 << num_bits_lookup);
         let spread_config = SpreadConfig::configure(meta, num_bits_lookup, num_advice_columns);
         Self {
             max_variable_byte_sizes,
@@ -71,548 +171,4721 @@ impl<F: PrimeField> Sha256DynamicConfig<F> {
             spread_config,
             cur_hash_idx: 0,
             is_input_range_check,
+            is_spread_table_loaded: std::cell::Cell::new(false),
+            round_constants: ROUND_CONSTANTS,
+            digest_set: None,
         }
     }
 
-    pub fn digest<'a, 'b: 'a>(
-        &'a mut self,
-        ctx: &mut Context<'b, F>,
-        input: &'a [u8],
-        precomputed_input_len: Option<usize>,
-    ) -> Result<AssignedHashResult<'b, F>, Error> {
-        let input_byte_size = input.len();
-        let input_byte_size_with_9 = input_byte_size + 9;
-        let one_round_size = Self::ONE_ROUND_INPUT_BYTES;
-        let num_round = if input_byte_size_with_9 % one_round_size == 0 {
-            input_byte_size_with_9 / one_round_size
-        } else {
-            input_byte_size_with_9 / one_round_size + 1
-        };
-        let padded_size = one_round_size * num_round;
-        let max_variable_byte_size = self.max_variable_byte_sizes[self.cur_hash_idx];
-        let max_variable_round = max_variable_byte_size / one_round_size;
-        let precomputed_input_len = precomputed_input_len.unwrap_or(0);
-        assert_eq!(precomputed_input_len % one_round_size, 0);
-        assert!(padded_size - precomputed_input_len <= max_variable_byte_size);
-        let zero_padding_byte_size = padded_size - input_byte_size_with_9;
-        let remaining_byte_size = max_variable_byte_size + precomputed_input_len - padded_size;
-        let precomputed_round = precomputed_input_len / one_round_size;
-        assert_eq!(
-            remaining_byte_size,
-            one_round_size * (max_variable_round + precomputed_round - num_round)
-        );
-        let mut padded_inputs = input.to_vec();
-        padded_inputs.push(0x80);
-        for _ in 0..zero_padding_byte_size {
-            padded_inputs.push(0);
+    /// Same as `configure`, but assigns the spread limbs to the given dense and spread advice
+    /// columns instead of allocating new ones.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the spread table does not fit in the usable rows of `range`.
+    pub fn configure_with_spread_columns(
+        meta: &mut ConstraintSystem<F>,
+        max_variable_byte_sizes: Vec<usize>,
+        range: RangeConfig<F>,
+        num_bits_lookup: usize,
+        dense_columns: Vec<Column<Advice>>,
+        spread_columns: Vec<Column<Advice>>,
+        is_input_range_check: bool,
+    ) -> Self {
+        for byte in max_variable_byte_sizes.iter() {
+            debug_assert_eq!(byte % Self::ONE_ROUND_INPUT_BYTES, 0);
         }
-        let mut input_len_bytes = [0; 8];
-        let le_size_bytes = (8 * input_byte_size).to_le_bytes();
-        input_len_bytes[0..le_size_bytes.len()].copy_from_slice(&le_size_bytes);
-        for byte in input_len_bytes.iter().rev() {
-            padded_inputs.push(*byte);
+        Self::assert_spread_table_size(&range, 1 << num_bits_lookup);
+        let spread_config = SpreadConfig::configure_with_columns(
+            meta,
+            num_bits_lookup,
+            dense_columns,
+            spread_columns,
+        );
+        Self {
+            max_variable_byte_sizes,
+            range,
+            spread_config,
+            cur_hash_idx: 0,
+            is_input_range_check,
+            is_spread_table_loaded: std::cell::Cell::new(false),
+            round_constants: ROUND_CONSTANTS,
+            digest_set: None,
         }
+    }
 
-        assert_eq!(padded_inputs.len(), num_round * one_round_size);
-        for _ in 0..remaining_byte_size {
-            padded_inputs.push(0);
+    /// Same as `configure` with 4-bit limbs, but each spread lookup checks two limbs at once
+    /// against a combined table of all 256 limb pairs, using `num_advice_columns` groups of four
+    /// advice columns. This gives 8-bit limb lookup counts with 4-bit limb decompositions.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the combined table does not fit in the usable rows of `range`.
+    pub fn configure_with_composite_spread(
+        meta: &mut ConstraintSystem<F>,
+        max_variable_byte_sizes: Vec<usize>,
+        range: RangeConfig<F>,
+        num_advice_columns: usize,
+        is_input_range_check: bool,
+    ) -> Self {
+        for byte in max_variable_byte_sizes.iter() {
+            debug_assert_eq!(byte % Self::ONE_ROUND_INPUT_BYTES, 0);
+        }
+        let spread_config = SpreadConfig::configure_composite(meta, num_advice_columns);
+        Self::assert_spread_table_size(&range, spread_config.table_size());
+        Self {
+            max_variable_byte_sizes,
+            range,
+            spread_config,
+            cur_hash_idx: 0,
+            is_input_range_check,
+            is_spread_table_loaded: std::cell::Cell::new(false),
+            round_constants: ROUND_CONSTANTS,
+            digest_set: None,
         }
+    }
+
+    /// Same as `configure`, taking the parameters from `params`.
+    ///
+    /// # Panics
+    ///
+    /// Panics unless `range` was configured for the same `k`, or if the spread table does not
+    /// fit in its usable rows.
+    pub fn configure_with_params(
+        meta: &mut ConstraintSystem<F>,
+        range: RangeConfig<F>,
+        params: &Sha256ConfigParams,
+    ) -> Self {
+        let range_k = Self::range_k(&range);
         assert_eq!(
-            padded_inputs.len(),
-            max_variable_byte_size + precomputed_input_len
+            range_k, params.k,
+            "the range config is for k = {} but the params are for k = {}",
+            range_k, params.k
         );
-        // for (idx, byte) in padded_inputs.iter().enumerate() {
-        //     println!("idx {} byte {}", idx, byte);
-        // }
+        Self::configure(
+            meta,
+            params.max_variable_byte_sizes.clone(),
+            range,
+            params.num_bits_lookup,
+            params.num_spread_columns,
+            params.is_input_range_check,
+        )
+    }
 
-        let range = self.range().clone();
-        let gate = range.gate();
-        let assigned_input_byte_size =
-            gate.load_witness(ctx, Value::known(F::from(input_byte_size as u64)));
-        let assigned_num_round = gate.load_witness(ctx, Value::known(F::from(num_round as u64)));
-        let assigned_padded_size = gate.mul(
-            ctx,
-            QuantumCell::Existing(&assigned_num_round),
-            QuantumCell::Constant(F::from(one_round_size as u64)),
-        );
-        let assigned_input_with_9_size = gate.add(
-            ctx,
-            QuantumCell::Existing(&assigned_input_byte_size),
-            QuantumCell::Constant(F::from(9u64)),
-        );
-        let padding_size = gate.sub(
-            ctx,
-            QuantumCell::Existing(&assigned_padded_size),
-            QuantumCell::Existing(&assigned_input_with_9_size),
-        );
-        let padding_is_less_than_round =
-            range.is_less_than_safe(ctx, &padding_size, one_round_size as u64);
-        gate.assert_is_const(ctx, &padding_is_less_than_round, F::one());
-        let assigned_precomputed_round =
-            gate.load_witness(ctx, Value::known(F::from(precomputed_round as u64)));
-        let assigned_target_round = gate.sub(
-            ctx,
-            QuantumCell::Existing(&assigned_num_round),
-            QuantumCell::Existing(&assigned_precomputed_round),
-        );
+    /// Checks that a spread table of `table_size` rows, e.g. `1 << num_bits_lookup`, fits in the
+    /// usable rows of `range`, so the parameters can be validated before configuring.
+    /// Fails with `Error::NotEnoughRowsAvailable` if it does not.
+    pub fn check_spread_table_size(range: &RangeConfig<F>, table_size: usize) -> Result<(), Error> {
+        if table_size > range.gate.max_rows {
+            return Err(Error::NotEnoughRowsAvailable {
+                current_k: Self::range_k(range),
+            });
+        }
+        Ok(())
+    }
+
+    fn assert_spread_table_size(range: &RangeConfig<F>, table_size: usize) {
+        if Self::check_spread_table_size(range, table_size).is_err() {
+            panic!(
+                "the spread table has {} rows but only {} usable rows are available; use a larger k or fewer lookup bits",
+                table_size,
+                range.gate.max_rows
+            );
+        }
+    }
+
+    // `max_rows` is `2^k` minus the few blinding rows, so its bit length is the range's `k`.
+    fn range_k(range: &RangeConfig<F>) -> u32 {
+        usize::BITS - range.gate.max_rows.leading_zeros()
+    }
+
+    pub fn digest<'a, 'b: 'a>(
+        &'a mut self,
+        ctx: &mut Context<'b, F>,
+        input: &'a [u8],
+        precomputed_input_len: Option<usize>,
+    ) -> Result<AssignedHashResult<'b, F>, Error> {
+        let precomputed_input_len = precomputed_input_len.unwrap_or(0);
+        let padded_inputs = self.pad_inputs(input, precomputed_input_len);
 
         // compute an initial state from the precomputed_input.
         let precomputed_input = &padded_inputs[0..precomputed_input_len];
         let mut last_state = INIT_STATE;
         let precomputed_blocks = precomputed_input
-            .chunks(one_round_size)
+            .chunks(Self::ONE_ROUND_INPUT_BYTES)
             .map(|bytes| GenericArray::clone_from_slice(bytes))
             .collect_vec();
         compress256(&mut last_state, &precomputed_blocks[..]);
 
-        let mut assigned_last_state_vec = vec![last_state
+        let range = self.range().clone();
+        let gate = range.gate();
+        let assigned_init_state = last_state
             .iter()
             .map(|state| gate.load_witness(ctx, Value::known(F::from(*state as u64))))
-            .collect_vec()];
+            .collect_vec();
         // vec![INIT_STATE
         //     .iter()
         //     .map(|h| gate.load_constant(ctx, F::from(*h as u64)))
         //     .collect::<Vec<AssignedValue<F>>>()];
-        let assigned_input_bytes = padded_inputs[precomputed_input_len..]
-            .iter()
-            .map(|byte| gate.load_witness(ctx, Value::known(F::from(*byte as u64))))
-            .collect::<Vec<AssignedValue<F>>>();
-        if self.is_input_range_check {
-            for assigned_byte in assigned_input_bytes.iter() {
-                range.range_check(ctx, assigned_byte, 8);
-            }
+        self.digest_with_state(
+            ctx,
+            &padded_inputs[precomputed_input_len..],
+            input.len(),
+            precomputed_input_len,
+            assigned_init_state,
+        )
+    }
+
+    /// Same as `digest`, additionally reporting the cells and lookups it used.
+    /// The counts depend only on the configured maximum size, not on `input`.
+    pub fn digest_with_stats<'a, 'b: 'a>(
+        &'a mut self,
+        ctx: &mut Context<'b, F>,
+        input: &'a [u8],
+        precomputed_input_len: Option<usize>,
+    ) -> Result<(AssignedHashResult<'b, F>, DigestStats), Error> {
+        let advice_cells = ctx.total_advice;
+        let range_lookup_cells = ctx.cells_to_lookup.len();
+        let spread_lookups = self.spread_config.num_lookups();
+        let result = self.digest(ctx, input, precomputed_input_len)?;
+        let stats = DigestStats {
+            advice_cells: ctx.total_advice - advice_cells,
+            range_lookup_cells: ctx.cells_to_lookup.len() - range_lookup_cells,
+            spread_lookups: self.spread_config.num_lookups() - spread_lookups,
+        };
+        Ok((result, stats))
+    }
+
+    /// Hashes `input` starting from the chaining state `iv` instead of the standard initial state.
+    /// `precomputed_input_len` is the number of bytes already absorbed into `iv` (a multiple of 64);
+    /// it only affects the length encoded in the padding.
+    pub fn digest_with_iv<'a, 'b: 'a>(
+        &'a mut self,
+        ctx: &mut Context<'b, F>,
+        input: &'a [u8],
+        iv: &[AssignedValue<'b, F>],
+        precomputed_input_len: usize,
+    ) -> Result<AssignedHashResult<'b, F>, Error> {
+        assert_eq!(iv.len(), NUM_STATE_WORD);
+        let range = self.range().clone();
+        for word in iv.iter() {
+            range.range_check(ctx, word, 32);
         }
-        let mut num_processed_input = 0;
-        while num_processed_input < max_variable_byte_size {
-            let assigned_input_word_at_round =
-                &assigned_input_bytes[num_processed_input..(num_processed_input + one_round_size)];
-            let new_assigned_hs_out = sha256_compression(
-                ctx,
-                &range,
-                &mut self.spread_config,
-                assigned_input_word_at_round,
-                &assigned_last_state_vec.last().unwrap(),
-            )?;
+        let full_input = [vec![0; precomputed_input_len], input.to_vec()].concat();
+        let padded_inputs = self.pad_inputs(&full_input, precomputed_input_len);
+        self.digest_with_state(
+            ctx,
+            &padded_inputs[precomputed_input_len..],
+            full_input.len(),
+            precomputed_input_len,
+            iv.to_vec(),
+        )
+    }
 
-            // let (witness, next_hs) = sha2_comp_config.compute_witness(
-            //     &padded_inputs[num_processed_input..(num_processed_input + one_round_size)],
-            //     last_hs,
-            // );
+    /// Computes the length-extension digest `SHA256(m || pad(m) || extension)` from
+    /// `digest = SHA256(m)` and `original_len = m.len()`, without knowing `m`.
+    /// `digest` is expected to be range-checked bytes, e.g. `AssignedHashResult::output_bytes`.
+    pub fn digest_length_extension<'a, 'b: 'a>(
+        &'a mut self,
+        ctx: &mut Context<'b, F>,
+        digest: &[AssignedValue<'b, F>],
+        original_len: usize,
+        extension: &'a [u8],
+    ) -> Result<AssignedHashResult<'b, F>, Error> {
+        assert_eq!(digest.len(), 4 * NUM_STATE_WORD);
+        let gate = self.range().gate().clone();
+        let state = digest
+            .chunks(4)
+            .map(|word_bytes| compose_be_bytes(ctx, &gate, word_bytes))
+            .collect_vec();
+        let padded_original_len = num_blocks_for_len(original_len) * Self::ONE_ROUND_INPUT_BYTES;
+        self.digest_with_iv(ctx, extension, &state, padded_original_len)
+    }
 
-            // last_hs = next_hs;
-            // let mut assigned_rows: Sha256AssignedRows<F> =
-            //     Sha256AssignedRows::<F>::new(self.num_consumed_rows);
-            // sha2_comp_config.assign_witness(&mut ctx.region, &witness, &mut assigned_rows)?;
-            // let assigned_h_ins: Vec<Vec<AssignedCell<F, F>>> = assigned_rows.get_h_ins();
-            // debug_assert_eq!(assigned_h_ins.len(), 1);
-            // let assigned_h_outs: Vec<Vec<AssignedCell<F, F>>> = assigned_rows.get_h_outs();
-            // debug_assert_eq!(assigned_h_outs.len(), 1);
-            // let assigned_input_words = assigned_rows.get_input_words();
-            // debug_assert_eq!(assigned_input_words.len(), 1);
+    /// Hashes `input` and constrains its length to equal the assigned `len`, e.g. a value
+    /// derived from an earlier digest. `len` is bounded by the next entry of
+    /// `max_variable_byte_sizes` so that the padded message fits.
+    pub fn digest_var_len<'a, 'b: 'a>(
+        &'a mut self,
+        ctx: &mut Context<'b, F>,
+        input: &'a [u8],
+        len: &AssignedValue<'b, F>,
+    ) -> Result<AssignedHashResult<'b, F>, Error> {
+        let max_variable_byte_size = self.max_variable_byte_sizes[self.cur_hash_idx];
+        let range = self.range().clone();
+        range.check_less_than_safe(ctx, len, (max_variable_byte_size - 8) as u64);
+        let result = self.digest(ctx, input, None)?;
+        range.gate().assert_equal(
+            ctx,
+            QuantumCell::Existing(&result.input_len),
+            QuantumCell::Existing(len),
+        );
+        Ok(result)
+    }
 
-            // 1. Constrain input bytes.
-            // for word_idx in 0..16 {
-            //     let assigned_input_u32 =
-            //         &assigned_input_word_at_round[4 * word_idx..4 * (word_idx + 1)];
-            //     let mut sum = gate.load_zero(ctx);
-            //     for (idx, assigned_byte) in assigned_input_u32.iter().enumerate() {
-            //         sum = gate.mul_add(
-            //             ctx,
-            //             QuantumCell::Existing(assigned_byte),
-            //             QuantumCell::Constant(F::from(1u64 << (8 * idx))),
-            //             QuantumCell::Existing(&sum),
-            //         );
-            //     }
-            //     ctx.region
-            //         .constrain_equal(sum.cell(), assigned_input_words[0][word_idx].cell())?;
-            // }
-            // 2. Constrain the previous h_out == current h_in.
-            // for (h_out, h_in) in assigned_last_hs_vec[assigned_last_hs_vec.len() - 1]
-            //     .iter()
-            //     .zip(assigned_h_ins[0].iter())
-            // {
-            //     ctx.region.constrain_equal(h_out.cell(), h_in.cell())?;
-            // }
-            // 3. Push the current h_out to assigned_last_hs_vec.
-            // let mut new_assigned_hs_out = vec![];
-            // for h_out in assigned_h_outs[0].iter() {
-            //     let assigned_on_gate = self.assigned_cell2value(ctx, h_out)?;
-            //     new_assigned_hs_out.push(assigned_on_gate)
-            // }
-            assigned_last_state_vec.push(new_assigned_hs_out);
-            num_processed_input += one_round_size;
+    /// Hashes the private `input` and proves `min_len <= input.len() <= max_len` for the public
+    /// bounds, e.g. to validate the format of a committed preimage without revealing it.
+    /// Expose `output_bytes` only; the input bytes and length stay private.
+    pub fn prove_membership_of_preimage_length<'a, 'b: 'a>(
+        &'a mut self,
+        ctx: &mut Context<'b, F>,
+        input: &'a [u8],
+        min_len: usize,
+        max_len: usize,
+    ) -> Result<AssignedHashResult<'b, F>, Error> {
+        assert!(min_len <= max_len);
+        let range = self.range().clone();
+        let gate = range.gate();
+        let len = gate.load_witness(ctx, Value::known(F::from(input.len() as u64)));
+        // wraps around to a huge value if `len < min_len`.
+        let offset = gate.sub(
+            ctx,
+            QuantumCell::Existing(&len),
+            QuantumCell::Constant(F::from(min_len as u64)),
+        );
+        range.check_less_than_safe(ctx, &offset, (max_len - min_len + 1) as u64);
+        self.digest_var_len(ctx, input, &len)
+    }
+
+    /// Absorbs `prefix`, a whole number of 64-byte blocks, and returns the chaining value
+    /// (midstate) as a commitment to it. Open it with `reveal`. The prefix bytes are range
+    /// checked here; no entry of `max_variable_byte_sizes` is consumed.
+    pub fn commit<'b>(
+        &mut self,
+        ctx: &mut Context<'b, F>,
+        prefix: &[u8],
+    ) -> Result<Vec<AssignedValue<'b, F>>, Error> {
+        assert_eq!(prefix.len() % Self::ONE_ROUND_INPUT_BYTES, 0);
+        let range = self.range().clone();
+        let gate = range.gate();
+        let mut state = INIT_STATE
+            .iter()
+            .map(|h| gate.load_constant(ctx, F::from(*h as u64)))
+            .collect_vec();
+        for block in prefix.chunks(Self::ONE_ROUND_INPUT_BYTES) {
+            let assigned_block = block
+                .iter()
+                .map(|byte| {
+                    let assigned = gate.load_witness(ctx, Value::known(F::from(*byte as u64)));
+                    range.range_check(ctx, &assigned, 8);
+                    assigned
+                })
+                .collect_vec();
+            state = self.compress_block(ctx, &state, &assigned_block)?;
         }
+        Ok(state)
+    }
 
-        // for n_column in 0..num_column {
-        //     let sha2_comp_config = &self.sha256_comp_configs[n_column];
-        //     for n_round in 0..num_round_per_column {
-        //         let round_idx = n_column * num_round_per_column + n_round;
-        //         let (witness, next_hs) = sha2_comp_config.compute_witness(
-        //             &padded_inputs[round_idx * one_round_size..(round_idx + 1) * one_round_size],
-        //             last_hs,
-        //         );
-        //         last_hs = next_hs;
-        //         let mut assigned_rows = Sha256AssignedRows::<F>::new(
-        //             n_round * Sha256CompressionConfig::<F>::ROWS_PER_BLOCK,
-        //         );
-        //         sha2_comp_config.assign_witness(&mut ctx.region, &witness, &mut assigned_rows)?;
-        //         let assigned_h_ins = assigned_rows.get_h_ins();
-        //         debug_assert_eq!(assigned_h_ins.len(), 1);
-        //         let assigned_h_outs = assigned_rows.get_h_outs();
-        //         debug_assert_eq!(assigned_h_outs.len(), 1);
-        //         let assigned_input_words = assigned_rows.get_input_words();
-        //         debug_assert_eq!(assigned_input_words.len(), 1);
-        //         let assigned_input_word_at_round = &assigned_input_bytes
-        //             [round_idx * one_round_size..(round_idx + 1) * one_round_size];
-        //         // 1. Constrain input bytes.
-        //         for word_idx in 0..16 {
-        //             let assigned_input_u32 =
-        //                 &assigned_input_word_at_round[4 * word_idx..4 * (word_idx + 1)];
-        //             let mut sum = gate.load_zero(ctx);
-        //             for (idx, assigned_byte) in assigned_input_u32.iter().enumerate() {
-        //                 sum = gate.mul_add(
-        //                     ctx,
-        //                     QuantumCell::Existing(assigned_byte),
-        //                     QuantumCell::Constant(F::from(1u64 << (8 * idx))),
-        //                     QuantumCell::Existing(&sum),
-        //                 );
-        //             }
-        //             ctx.region
-        //                 .constrain_equal(sum.cell(), assigned_input_words[0][word_idx].cell())?;
-        //         }
-        //         // 2. Constrain the previous h_out == current h_in.
-        //         for (h_out, h_in) in assigned_last_hs_vec[assigned_last_hs_vec.len() - 1]
-        //             .iter()
-        //             .zip(assigned_h_ins[0].iter())
-        //         {
-        //             ctx.region.constrain_equal(h_out.cell(), h_in.cell())?;
-        //         }
-        //         // 3. Push the current h_out to assigned_last_hs_vec.
-        //         let mut new_assigned_hs_out = vec![];
-        //         for h_out in assigned_h_outs[0].iter() {
-        //             let assigned_on_gate = self.assigned_cell2value(ctx, h_out)?;
-        //             new_assigned_hs_out.push(assigned_on_gate)
-        //         }
-        //         assigned_last_hs_vec.push(new_assigned_hs_out);
-        //     }
-        // }
+    /// Proves that `midstate = commit(prefix)` for some `prefix` of `prefix_len` bytes by
+    /// finishing the hash, returning `SHA256(prefix || suffix)`. The midstate words are range
+    /// checked, so a commitment assigned elsewhere can be fed back directly.
+    pub fn reveal<'a, 'b: 'a>(
+        &'a mut self,
+        ctx: &mut Context<'b, F>,
+        midstate: &[AssignedValue<'b, F>],
+        suffix: &'a [u8],
+        prefix_len: usize,
+    ) -> Result<AssignedHashResult<'b, F>, Error> {
+        self.digest_with_iv(ctx, suffix, midstate, prefix_len)
+    }
 
-        let zero = gate.load_zero(ctx);
-        let mut output_h_out = vec![zero; 8];
-        for (n_round, assigned_state) in assigned_last_state_vec.into_iter().enumerate() {
-            let selector = gate.is_equal(
+    /// Hashes `input` and returns, along with the result, the input byte cells whose entry in
+    /// `public_mask` is `true`. The caller must constrain those cells to instance cells (e.g. with
+    /// `Layouter::constrain_instance`) to reveal them; the remaining bytes stay private.
+    pub fn digest_mixed<'a, 'b: 'a>(
+        &'a mut self,
+        ctx: &mut Context<'b, F>,
+        input: &'a [u8],
+        public_mask: &[bool],
+    ) -> Result<(AssignedHashResult<'b, F>, Vec<AssignedValue<'b, F>>), Error> {
+        assert_eq!(input.len(), public_mask.len());
+        let result = self.digest(ctx, input, None)?;
+        let public_bytes = result
+            .input_bytes
+            .iter()
+            .zip(public_mask.iter())
+            .filter(|(_, is_public)| **is_public)
+            .map(|(byte, _)| byte.clone())
+            .collect_vec();
+        Ok((result, public_bytes))
+    }
+
+    /// Computes `SHA256(tag || msg)` with the tag bytes constrained to the given constants.
+    pub fn digest_tagged<'a, 'b: 'a>(
+        &'a mut self,
+        ctx: &mut Context<'b, F>,
+        tag: &[u8],
+        msg: &'a [u8],
+    ) -> Result<AssignedHashResult<'b, F>, Error> {
+        let input = [tag, msg].concat();
+        let result = self.digest(ctx, &input, None)?;
+        let gate = self.range.gate();
+        for (assigned_byte, byte) in result.input_bytes.iter().zip(tag.iter()) {
+            gate.assert_is_const(ctx, assigned_byte, F::from(*byte as u64));
+        }
+        Ok(result)
+    }
+
+    /// Computes the BIP-340 tagged hash `SHA256(SHA256(tag) || SHA256(tag) || msg)`.
+    pub fn digest_bip340_tagged<'a, 'b: 'a>(
+        &'a mut self,
+        ctx: &mut Context<'b, F>,
+        tag: &[u8],
+        msg: &'a [u8],
+    ) -> Result<AssignedHashResult<'b, F>, Error> {
+        let tag_hash = Sha256::digest(tag);
+        self.digest_tagged(
+            ctx,
+            &[tag_hash.as_slice(), tag_hash.as_slice()].concat(),
+            msg,
+        )
+    }
+
+    /// Computes `SHA256(len(msg) as u32 big-endian || msg)`. The prefix bytes are constrained to
+    /// encode the hashed length minus the 4 prefix bytes, so they cannot disagree with `msg`.
+    pub fn digest_length_prefixed<'a, 'b: 'a>(
+        &'a mut self,
+        ctx: &mut Context<'b, F>,
+        msg: &'a [u8],
+    ) -> Result<AssignedHashResult<'b, F>, Error> {
+        let prefix = (msg.len() as u32).to_be_bytes();
+        let input = [&prefix[..], msg].concat();
+        let result = self.digest(ctx, &input, None)?;
+        let gate = self.range.gate();
+        let assigned_prefix = compose_be_bytes(ctx, gate, &result.input_bytes[0..4]);
+        let msg_len = gate.sub(
+            ctx,
+            QuantumCell::Existing(&result.input_len),
+            QuantumCell::Constant(F::from(prefix.len() as u64)),
+        );
+        gate.assert_equal(
+            ctx,
+            QuantumCell::Existing(&assigned_prefix),
+            QuantumCell::Existing(&msg_len),
+        );
+        Ok(result)
+    }
+
+    /// Hashes the 4-byte big-endian encoding of `x`, constraining `x` to 32 bits.
+    pub fn digest_u32<'b>(
+        &mut self,
+        ctx: &mut Context<'b, F>,
+        x: &AssignedValue<'b, F>,
+    ) -> Result<AssignedHashResult<'b, F>, Error> {
+        self.digest_be_integer(ctx, x, 4)
+    }
+
+    /// Hashes the 8-byte big-endian encoding of `x`, constraining `x` to 64 bits.
+    pub fn digest_u64<'b>(
+        &mut self,
+        ctx: &mut Context<'b, F>,
+        x: &AssignedValue<'b, F>,
+    ) -> Result<AssignedHashResult<'b, F>, Error> {
+        self.digest_be_integer(ctx, x, 8)
+    }
+
+    fn digest_be_integer<'b>(
+        &mut self,
+        ctx: &mut Context<'b, F>,
+        x: &AssignedValue<'b, F>,
+        num_bytes: usize,
+    ) -> Result<AssignedHashResult<'b, F>, Error> {
+        let range = self.range().clone();
+        let gate = range.gate();
+        let be_bytes_val = x
+            .value()
+            .map(|v| v.get_lower_128().to_be_bytes()[(16 - num_bytes)..].to_vec());
+        let assigned_bytes = (0..num_bytes)
+            .map(|idx| {
+                let assigned = gate.load_witness(
+                    ctx,
+                    be_bytes_val
+                        .as_ref()
+                        .map(|bytes| F::from(bytes[idx] as u64)),
+                );
+                range.range_check(ctx, &assigned, 8);
+                assigned
+            })
+            .collect_vec();
+        let composed = compose_be_bytes(ctx, gate, &assigned_bytes);
+        gate.assert_equal(
+            ctx,
+            QuantumCell::Existing(&composed),
+            QuantumCell::Existing(x),
+        );
+        self.digest_assigned_bytes(ctx, &assigned_bytes)
+    }
+
+    /// Hashes the concatenation of the 32-byte big-endian encodings of `words`, e.g. to commit
+    /// to many field elements at once. Each encoding is constrained to be the canonical one,
+    /// i.e. less than the field modulus.
+    pub fn digest_words32<'b>(
+        &mut self,
+        ctx: &mut Context<'b, F>,
+        words: &[AssignedValue<'b, F>],
+    ) -> Result<AssignedHashResult<'b, F>, Error> {
+        let range = self.range().clone();
+        let modulus_bytes = modulus::<F>().to_bytes_be();
+        assert!(modulus_bytes.len() <= 32);
+        let modulus_bytes = [vec![0u8; 32 - modulus_bytes.len()], modulus_bytes].concat();
+        let mut assigned_bytes = vec![];
+        for word in words.iter() {
+            let be_bytes_val = word.value().map(|v| {
+                let bytes = fe_to_biguint(v).to_bytes_be();
+                [vec![0u8; 32 - bytes.len()], bytes].concat()
+            });
+            let word_bytes = (0..32)
+                .map(|idx| {
+                    let assigned = range.gate().load_witness(
+                        ctx,
+                        be_bytes_val
+                            .as_ref()
+                            .map(|bytes| F::from(bytes[idx] as u64)),
+                    );
+                    range.range_check(ctx, &assigned, 8);
+                    assigned
+                })
+                .collect_vec();
+            let composed = compose_be_bytes(ctx, range.gate(), &word_bytes);
+            range.gate().assert_equal(
                 ctx,
-                QuantumCell::Constant(F::from(n_round as u64)),
-                QuantumCell::Existing(&assigned_target_round),
+                QuantumCell::Existing(&composed),
+                QuantumCell::Existing(word),
             );
-            for i in 0..8 {
-                output_h_out[i] = gate.select(
+            assert_be_bytes_less_than(ctx, &range, &word_bytes, &modulus_bytes);
+            assigned_bytes.extend(word_bytes);
+        }
+        self.digest_assigned_bytes(ctx, &assigned_bytes)
+    }
+
+    /// Hashes a message given as nibbles, most significant nibble of each byte first.
+    /// Every nibble is constrained to 4 bits and each pair is recombined into one byte.
+    pub fn digest_nibbles<'b>(
+        &mut self,
+        ctx: &mut Context<'b, F>,
+        nibbles: &[AssignedValue<'b, F>],
+    ) -> Result<AssignedHashResult<'b, F>, Error> {
+        assert_eq!(nibbles.len() % 2, 0);
+        let range = self.range().clone();
+        let gate = range.gate();
+        let assigned_bytes = nibbles
+            .chunks(2)
+            .map(|pair| {
+                range.range_check(ctx, &pair[0], 4);
+                range.range_check(ctx, &pair[1], 4);
+                gate.mul_add(
                     ctx,
-                    QuantumCell::Existing(&assigned_state[i]),
-                    QuantumCell::Existing(&output_h_out[i]),
-                    QuantumCell::Existing(&selector),
+                    QuantumCell::Existing(&pair[0]),
+                    QuantumCell::Constant(F::from(16u64)),
+                    QuantumCell::Existing(&pair[1]),
                 )
+            })
+            .collect_vec();
+        self.digest_assigned_bytes(ctx, &assigned_bytes)
+    }
+
+    /// Computes the prefix MAC `SHA256(key || msg)` for legacy protocols that specify it.
+    /// Unlike HMAC it is vulnerable to length extension (see `digest_length_extension`), so
+    /// prefer `hmac` for new designs. `key` and `msg` must be range-checked bytes.
+    pub fn digest_prefix_mac<'b>(
+        &mut self,
+        ctx: &mut Context<'b, F>,
+        key: &[AssignedValue<'b, F>],
+        msg: &[AssignedValue<'b, F>],
+    ) -> Result<AssignedHashResult<'b, F>, Error> {
+        self.digest_assigned_bytes(ctx, &[key, msg].concat())
+    }
+
+    /// Computes `SHA256(prefix || field)` if `present` is 1 and `SHA256(prefix)` if it is 0.
+    /// The circuit is the same in both cases, so the maximum size must have room for `field`.
+    /// `prefix` and `field` must be range-checked bytes.
+    pub fn digest_optional<'b>(
+        &mut self,
+        ctx: &mut Context<'b, F>,
+        prefix: &[AssignedValue<'b, F>],
+        field: &[AssignedValue<'b, F>],
+        present: &AssignedValue<'b, F>,
+    ) -> Result<AssignedHashResult<'b, F>, Error> {
+        let range = self.range().clone();
+        let gate = range.gate();
+        range.range_check(ctx, present, 1);
+        let mut is_present = false;
+        present.value().map(|v| is_present = *v == F::one());
+        let mut bytes = vec![0u8; prefix.len()];
+        for (byte, assigned) in bytes.iter_mut().zip(prefix.iter()) {
+            assigned.value().map(|v| *byte = v.get_lower_32() as u8);
+        }
+        if is_present {
+            for assigned in field.iter() {
+                let mut byte = 0u8;
+                assigned.value().map(|v| byte = v.get_lower_32() as u8);
+                bytes.push(byte);
             }
         }
-        let output_digest_bytes = output_h_out
-            .into_iter()
-            .flat_map(|assigned_word| {
-                let be_bytes = assigned_word
-                    .value()
-                    .map(|v| v.get_lower_32().to_be_bytes().to_vec());
-                let assigned_bytes = (0..4)
-                    .map(|idx| {
-                        let assigned = gate
-                            .load_witness(ctx, be_bytes.as_ref().map(|vs| F::from(vs[idx] as u64)));
-                        range.range_check(ctx, &assigned, 8);
-                        assigned
-                    })
-                    .collect::<Vec<AssignedValue<F>>>();
-                let mut sum = gate.load_zero(ctx);
-                for (idx, assigned_byte) in assigned_bytes.iter().enumerate() {
-                    sum = gate.mul_add(
+        let result = self.digest(ctx, &bytes, None)?;
+
+        let expected_len = gate.mul_add(
+            ctx,
+            QuantumCell::Existing(present),
+            QuantumCell::Constant(F::from(field.len() as u64)),
+            QuantumCell::Constant(F::from(prefix.len() as u64)),
+        );
+        gate.assert_equal(
+            ctx,
+            QuantumCell::Existing(&result.input_len),
+            QuantumCell::Existing(&expected_len),
+        );
+        for (hashed, assigned) in result.input_bytes.iter().zip(prefix.iter()) {
+            gate.assert_equal(
+                ctx,
+                QuantumCell::Existing(hashed),
+                QuantumCell::Existing(assigned),
+            );
+        }
+        for (hashed, assigned) in result.input_bytes[prefix.len()..].iter().zip(field.iter()) {
+            // only binds the field bytes when it is present.
+            let expected = gate.select(
+                ctx,
+                QuantumCell::Existing(assigned),
+                QuantumCell::Existing(hashed),
+                QuantumCell::Existing(present),
+            );
+            gate.assert_equal(
+                ctx,
+                QuantumCell::Existing(hashed),
+                QuantumCell::Existing(&expected),
+            );
+        }
+        Ok(result)
+    }
+
+    /// Commits to several digests at once as `SHA256(digests[0] || digests[1] || ...)`, so a
+    /// batch of statements can expose a single commitment instead of every digest. Consumes
+    /// the next entry of `max_variable_byte_sizes`, which must fit `32 * digests.len()` bytes.
+    pub fn aggregate_digests<'b>(
+        &mut self,
+        ctx: &mut Context<'b, F>,
+        digests: &[Vec<AssignedValue<'b, F>>],
+    ) -> Result<AssignedHashResult<'b, F>, Error> {
+        for digest in digests.iter() {
+            assert_eq!(digest.len(), 32);
+        }
+        self.digest_assigned_bytes(ctx, &digests.concat())
+    }
+
+    // Hashes the message held in `assigned_bytes`, which must already be constrained to bytes,
+    // by digesting their witness values and tying the hashed input cells back to them.
+    fn digest_assigned_bytes<'b>(
+        &mut self,
+        ctx: &mut Context<'b, F>,
+        assigned_bytes: &[AssignedValue<'b, F>],
+    ) -> Result<AssignedHashResult<'b, F>, Error> {
+        let mut bytes = vec![0u8; assigned_bytes.len()];
+        for (byte, assigned) in bytes.iter_mut().zip(assigned_bytes.iter()) {
+            assigned.value().map(|v| *byte = v.get_lower_32() as u8);
+        }
+        let result = self.digest(ctx, &bytes, None)?;
+        let gate = self.range.gate();
+        gate.assert_is_const(ctx, &result.input_len, F::from(bytes.len() as u64));
+        for (hashed, assigned) in result.input_bytes.iter().zip(assigned_bytes.iter()) {
+            gate.assert_equal(
+                ctx,
+                QuantumCell::Existing(hashed),
+                QuantumCell::Existing(assigned),
+            );
+        }
+        Ok(result)
+    }
+
+    /// Computes the digest of a message that is exactly one 64-byte `block`, e.g. a Merkle tree
+    /// node built with `Block::from_halves`. The second (padding) block is constant, so unlike
+    /// `digest` there is no length-dependent block selection and no entry of
+    /// `max_variable_byte_sizes` is consumed. The input bytes are not range checked: they are
+    /// expected to be range-checked cells such as digest bytes.
+    pub fn digest_block<'b>(
+        &mut self,
+        ctx: &mut Context<'b, F>,
+        block: &Block<'b, F>,
+    ) -> Result<Vec<AssignedValue<'b, F>>, Error> {
+        let range = self.range().clone();
+        let gate = range.gate();
+        let input_bytes = block.bytes();
+        let init_state = INIT_STATE
+            .iter()
+            .map(|h| gate.load_constant(ctx, F::from(*h as u64)))
+            .collect_vec();
+        let mid_state = self.compress_block(ctx, &init_state, input_bytes)?;
+        let mut padding = vec![0u8; Self::ONE_ROUND_INPUT_BYTES];
+        padding[0] = 0x80;
+        padding[56..].copy_from_slice(&(8 * Self::ONE_ROUND_INPUT_BYTES as u64).to_be_bytes());
+        let padding_bytes = padding
+            .iter()
+            .map(|byte| gate.load_constant(ctx, F::from(*byte as u64)))
+            .collect_vec();
+        let state = self.compress_block(ctx, &mid_state, &padding_bytes)?;
+        Ok(state_to_be_bytes(ctx, &range, state))
+    }
+
+    /// Computes the digest of an `nbits`-bit message given as big-endian `bits`, which need not
+    /// be a whole number of bytes. Padding is done at the bit level as in FIPS 180-4: a single 1
+    /// bit, zeros up to 448 mod 512 bits and the 64-bit message length in bits. `nbits` is fixed
+    /// at circuit construction, so the number of blocks is too and `max_variable_byte_sizes` is
+    /// not consumed. Each bit is constrained to be 0 or 1.
+    pub fn digest_bits<'b>(
+        &mut self,
+        ctx: &mut Context<'b, F>,
+        bits: &[AssignedValue<'b, F>],
+        nbits: usize,
+    ) -> Result<Vec<AssignedValue<'b, F>>, Error> {
+        assert_eq!(bits.len(), nbits);
+        let range = self.range().clone();
+        let gate = range.gate();
+        for bit in bits.iter() {
+            range.range_check(ctx, bit, 1);
+        }
+        let one_round_bits = 8 * Self::ONE_ROUND_INPUT_BYTES;
+        let num_round = (nbits + 1 + 64 + one_round_bits - 1) / one_round_bits;
+        let zero = gate.load_zero(ctx);
+        let one = gate.load_constant(ctx, F::one());
+        let mut padded_bits = bits.to_vec();
+        padded_bits.push(one.clone());
+        padded_bits.resize(num_round * one_round_bits - 64, zero.clone());
+        for idx in (0..64).rev() {
+            padded_bits.push(if (nbits as u64 >> idx) & 1 == 1 {
+                one.clone()
+            } else {
+                zero.clone()
+            });
+        }
+        let padded_bytes = padded_bits
+            .chunks(8)
+            .map(|byte_bits| {
+                let mut byte = gate.load_zero(ctx);
+                for bit in byte_bits.iter() {
+                    byte = gate.mul_add(
                         ctx,
-                        QuantumCell::Existing(assigned_byte),
-                        QuantumCell::Constant(F::from(1u64 << (24 - 8 * idx))),
-                        QuantumCell::Existing(&sum),
+                        QuantumCell::Existing(&byte),
+                        QuantumCell::Constant(F::from(2u64)),
+                        QuantumCell::Existing(bit),
                     );
                 }
-                gate.assert_equal(
-                    ctx,
-                    QuantumCell::Existing(&assigned_word),
-                    QuantumCell::Existing(&sum),
-                );
-                assigned_bytes
+                byte
             })
-            .collect::<Vec<AssignedValue<F>>>();
-        let result = AssignedHashResult {
-            input_len: assigned_input_byte_size,
-            input_bytes: assigned_input_bytes,
-            output_bytes: output_digest_bytes,
-        };
-        self.cur_hash_idx += 1;
-        Ok(result)
+            .collect_vec();
+        let mut state = INIT_STATE
+            .iter()
+            .map(|h| gate.load_constant(ctx, F::from(*h as u64)))
+            .collect_vec();
+        for block in padded_bytes.chunks(Self::ONE_ROUND_INPUT_BYTES) {
+            state = self.compress_block(ctx, &state, block)?;
+        }
+        Ok(state_to_be_bytes(ctx, &range, state))
     }
 
-    pub fn new_context<'a, 'b>(&'b self, region: Region<'a, F>) -> Context<'a, F> {
-        Context::new(
-            region,
-            ContextParams {
-                max_rows: self.range.gate.max_rows,
-                num_context_ids: 1,
-                fixed_columns: self.range.gate.constants.clone(),
-            },
+    /// Computes the digest of a sparse `len`-byte message given as `(position, value)` pairs;
+    /// every other byte is zero. Only the values are private witnesses: the zeros, the
+    /// positions and the padding are constants, so mostly-zero messages cost few advice cells
+    /// beyond the compressions. `len` is fixed, so no entry of `max_variable_byte_sizes` is
+    /// consumed.
+    pub fn digest_sparse<'b>(
+        &mut self,
+        ctx: &mut Context<'b, F>,
+        len: usize,
+        entries: &[(usize, u8)],
+    ) -> Result<Vec<AssignedValue<'b, F>>, Error> {
+        let range = self.range().clone();
+        let gate = range.gate();
+        let zero = gate.load_zero(ctx);
+        let mut padded = pad_message(&vec![0u8; len])
+            .iter()
+            .enumerate()
+            .map(|(idx, byte)| {
+                if idx < len {
+                    zero.clone()
+                } else {
+                    gate.load_constant(ctx, F::from(*byte as u64))
+                }
+            })
+            .collect_vec();
+        let mut is_assigned = vec![false; len];
+        for (position, value) in entries.iter() {
+            assert!(*position < len);
+            assert!(!is_assigned[*position], "duplicate position {}", position);
+            is_assigned[*position] = true;
+            let assigned = gate.load_witness(ctx, Value::known(F::from(*value as u64)));
+            range.range_check(ctx, &assigned, 8);
+            padded[*position] = assigned;
+        }
+        let mut state = INIT_STATE
+            .iter()
+            .map(|h| gate.load_constant(ctx, F::from(*h as u64)))
+            .collect_vec();
+        for block in padded.chunks(Self::ONE_ROUND_INPUT_BYTES) {
+            state = self.compress_block(ctx, &state, block)?;
+        }
+        Ok(state_to_be_bytes(ctx, &range, state))
+    }
+
+    /// Applies the compression function to one 64-byte block, independently of padding.
+    /// `state` and `block` are expected to be range-checked 32-bit words and bytes respectively.
+    pub fn compress_block<'b>(
+        &mut self,
+        ctx: &mut Context<'b, F>,
+        state: &[AssignedValue<'b, F>],
+        block: &[AssignedValue<'b, F>],
+    ) -> Result<Vec<AssignedValue<'b, F>>, Error> {
+        assert_eq!(state.len(), NUM_STATE_WORD);
+        assert_eq!(block.len(), Self::ONE_ROUND_INPUT_BYTES);
+        let range = self.range().clone();
+        let (next_state, _) = sha256_compression_with_round_constants(
+            ctx,
+            &range,
+            &mut self.spread_config,
+            block,
+            state,
+            &self.round_constants,
+        )?;
+        Ok(next_state)
+    }
+
+    /// Same as `compress_block`, but returns the working variables before the final addition of
+    /// `state`. Non-standard: see `sha256_compression_no_feed_forward`.
+    pub fn compress_block_no_feed_forward<'b>(
+        &mut self,
+        ctx: &mut Context<'b, F>,
+        state: &[AssignedValue<'b, F>],
+        block: &[AssignedValue<'b, F>],
+    ) -> Result<Vec<AssignedValue<'b, F>>, Error> {
+        assert_eq!(state.len(), NUM_STATE_WORD);
+        assert_eq!(block.len(), Self::ONE_ROUND_INPUT_BYTES);
+        let range = self.range().clone();
+        sha256_compression_no_feed_forward(ctx, &range, &mut self.spread_config, block, state)
+    }
+
+    fn pad_inputs(&self, input: &[u8], precomputed_input_len: usize) -> Vec<u8> {
+        let input_byte_size = input.len();
+        let one_round_size = Self::ONE_ROUND_INPUT_BYTES;
+        let num_round = num_blocks_for_len(input_byte_size);
+        let padded_size = one_round_size * num_round;
+        let max_variable_byte_size = self.max_variable_byte_sizes[self.cur_hash_idx];
+        let max_variable_round = max_variable_byte_size / one_round_size;
+        assert_eq!(precomputed_input_len % one_round_size, 0);
+        assert!(padded_size - precomputed_input_len <= max_variable_byte_size);
+        let remaining_byte_size = max_variable_byte_size + precomputed_input_len - padded_size;
+        let precomputed_round = precomputed_input_len / one_round_size;
+        assert_eq!(
+            remaining_byte_size,
+            one_round_size * (max_variable_round + precomputed_round - num_round)
+        );
+        let mut padded_inputs = pad_message(input);
+        assert_eq!(padded_inputs.len(), num_round * one_round_size);
+        for _ in 0..remaining_byte_size {
+            padded_inputs.push(0);
+        }
+        assert_eq!(
+            padded_inputs.len(),
+            max_variable_byte_size + precomputed_input_len
+        );
+        // for (idx, byte) in padded_inputs.iter().enumerate() {
+        //     println!("idx {} byte {}", idx, byte);
+        // }
+        padded_inputs
+    }
+
+    fn digest_with_state<'b>(
+        &mut self,
+        ctx: &mut Context<'b, F>,
+        padded_inputs: &[u8],
+        input_byte_size: usize,
+        precomputed_input_len: usize,
+        init_state: Vec<AssignedValue<'b, F>>,
+    ) -> Result<AssignedHashResult<'b, F>, Error> {
+        let one_round_size = Self::ONE_ROUND_INPUT_BYTES;
+        let num_round = num_blocks_for_len(input_byte_size);
+        let max_variable_byte_size = self.max_variable_byte_sizes[self.cur_hash_idx];
+        let precomputed_round = precomputed_input_len / one_round_size;
+        debug_assert_eq!(padded_inputs.len(), max_variable_byte_size);
+        #[cfg(feature = "tracing")]
+        let _span = tracing::info_span!(
+            "sha256_digest",
+            hash_idx = self.cur_hash_idx,
+            max_bytes = max_variable_byte_size
         )
+        .entered();
+
+        let range = self.range().clone();
+        let gate = range.gate();
+        let assigned_input_byte_size =
+            gate.load_witness(ctx, Value::known(F::from(input_byte_size as u64)));
+        let assigned_num_round = gate.load_witness(ctx, Value::known(F::from(num_round as u64)));
+        let assigned_padded_size = gate.mul(
+            ctx,
+            QuantumCell::Existing(&assigned_num_round),
+            QuantumCell::Constant(F::from(one_round_size as u64)),
+        );
+        let assigned_input_with_9_size = gate.add(
+            ctx,
+            QuantumCell::Existing(&assigned_input_byte_size),
+            QuantumCell::Constant(F::from(9u64)),
+        );
+        let padding_size = gate.sub(
+            ctx,
+            QuantumCell::Existing(&assigned_padded_size),
+            QuantumCell::Existing(&assigned_input_with_9_size),
+        );
+        let padding_is_less_than_round =
+            range.is_less_than_safe(ctx, &padding_size, one_round_size as u64);
+        gate.assert_is_const(ctx, &padding_is_less_than_round, F::one());
+        let assigned_precomputed_round =
+            gate.load_witness(ctx, Value::known(F::from(precomputed_round as u64)));
+        let assigned_target_round = gate.sub(
+            ctx,
+            QuantumCell::Existing(&assigned_num_round),
+            QuantumCell::Existing(&assigned_precomputed_round),
+        );
+
+        let mut assigned_last_state_vec = vec![init_state];
+        let assigned_input_bytes = padded_inputs
+            .iter()
+            .map(|byte| gate.load_witness(ctx, Value::known(F::from(*byte as u64))))
+            .collect::<Vec<AssignedValue<F>>>();
+        if self.is_input_range_check {
+            for assigned_byte in assigned_input_bytes.iter() {
+                range.range_check(ctx, assigned_byte, 8);
+            }
+        }
+        #[cfg(feature = "debug-schedule")]
+        let mut message_schedules = vec![];
+        let mut num_processed_input = 0;
+        while num_processed_input < max_variable_byte_size {
+            #[cfg(feature = "tracing")]
+            let _span = tracing::debug_span!(
+                "sha256_compression",
+                block = num_processed_input / one_round_size
+            )
+            .entered();
+            let assigned_input_word_at_round =
+                &assigned_input_bytes[num_processed_input..(num_processed_input + one_round_size)];
+            let (new_assigned_hs_out, _message_schedule) = sha256_compression_with_round_constants(
+                ctx,
+                &range,
+                &mut self.spread_config,
+                assigned_input_word_at_round,
+                &assigned_last_state_vec.last().unwrap(),
+                &self.round_constants,
+            )?;
+            #[cfg(feature = "debug-schedule")]
+            message_schedules.push(_message_schedule);
+
+            // let (witness, next_hs) = sha2_comp_config.compute_witness(
+            //     &padded_inputs[num_processed_input..(num_processed_input + one_round_size)],
+            //     last_hs,
+            // );
+
+            // last_hs = next_hs;
+            // let mut assigned_rows: Sha256AssignedRows<F> =
+            //     Sha256AssignedRows::<F>::new(self.num_consumed_rows);
+            // sha2_comp_config.assign_witness(&mut ctx.region, &witness, &mut assigned_rows)?;
+            // let assigned_h_ins: Vec<Vec<AssignedCell<F, F>>> = assigned_rows.get_h_ins();
+            // debug_assert_eq!(assigned_h_ins.len(), 1);
+            // let assigned_h_outs: Vec<Vec<AssignedCell<F, F>>> = assigned_rows.get_h_outs();
+            // debug_assert_eq!(assigned_h_outs.len(), 1);
+            // let assigned_input_words = assigned_rows.get_input_words();
+            // debug_assert_eq!(assigned_input_words.len(), 1);
+
+            // 1. Constrain input bytes.
+            // for word_idx in 0..16 {
+            //     let assigned_input_u32 =
+            //         &assigned_input_word_at_round[4 * word_idx..4 * (word_idx + 1)];
+            //     let mut sum = gate.load_zero(ctx);
+            //     for (idx, assigned_byte) in assigned_input_u32.iter().enumerate() {
+            //         sum = gate.mul_add(
+            //             ctx,
+            //             QuantumCell::Existing(assigned_byte),
+            //             QuantumCell::Constant(F::from(1u64 << (8 * idx))),
+            //             QuantumCell::Existing(&sum),
+            //         );
+            //     }
+            //     ctx.region
+            //         .constrain_equal(sum.cell(), assigned_input_words[0][word_idx].cell())?;
+            // }
+            // 2. Constrain the previous h_out == current h_in.
+            // for (h_out, h_in) in assigned_last_hs_vec[assigned_last_hs_vec.len() - 1]
+            //     .iter()
+            //     .zip(assigned_h_ins[0].iter())
+            // {
+            //     ctx.region.constrain_equal(h_out.cell(), h_in.cell())?;
+            // }
+            // 3. Push the current h_out to assigned_last_hs_vec.
+            // let mut new_assigned_hs_out = vec![];
+            // for h_out in assigned_h_outs[0].iter() {
+            //     let assigned_on_gate = self.assigned_cell2value(ctx, h_out)?;
+            //     new_assigned_hs_out.push(assigned_on_gate)
+            // }
+            assigned_last_state_vec.push(new_assigned_hs_out);
+            num_processed_input += one_round_size;
+        }
+
+        // for n_column in 0..num_column {
+        //     let sha2_comp_config = &self.sha256_comp_configs[n_column];
+        //     for n_round in 0..num_round_per_column {
+        //         let round_idx = n_column * num_round_per_column + n_round;
+        //         let (witness, next_hs) = sha2_comp_config.compute_witness(
+        //             &padded_inputs[round_idx * one_round_size..(round_idx + 1) * one_round_size],
+        //             last_hs,
+        //         );
+        //         last_hs = next_hs;
+        //         let mut assigned_rows = Sha256AssignedRows::<F>::new(
+        //             n_round * Sha256CompressionConfig::<F>::ROWS_PER_BLOCK,
+        //         );
+        //         sha2_comp_config.assign_witness(&mut ctx.region, &witness, &mut assigned_rows)?;
+        //         let assigned_h_ins = assigned_rows.get_h_ins();
+        //         debug_assert_eq!(assigned_h_ins.len(), 1);
+        //         let assigned_h_outs = assigned_rows.get_h_outs();
+        //         debug_assert_eq!(assigned_h_outs.len(), 1);
+        //         let assigned_input_words = assigned_rows.get_input_words();
+        //         debug_assert_eq!(assigned_input_words.len(), 1);
+        //         let assigned_input_word_at_round = &assigned_input_bytes
+        //             [round_idx * one_round_size..(round_idx + 1) * one_round_size];
+        //         // 1. Constrain input bytes.
+        //         for word_idx in 0..16 {
+        //             let assigned_input_u32 =
+        //                 &assigned_input_word_at_round[4 * word_idx..4 * (word_idx + 1)];
+        //             let mut sum = gate.load_zero(ctx);
+        //             for (idx, assigned_byte) in assigned_input_u32.iter().enumerate() {
+        //                 sum = gate.mul_add(
+        //                     ctx,
+        //                     QuantumCell::Existing(assigned_byte),
+        //                     QuantumCell::Constant(F::from(1u64 << (8 * idx))),
+        //                     QuantumCell::Existing(&sum),
+        //                 );
+        //             }
+        //             ctx.region
+        //                 .constrain_equal(sum.cell(), assigned_input_words[0][word_idx].cell())?;
+        //         }
+        //         // 2. Constrain the previous h_out == current h_in.
+        //         for (h_out, h_in) in assigned_last_hs_vec[assigned_last_hs_vec.len() - 1]
+        //             .iter()
+        //             .zip(assigned_h_ins[0].iter())
+        //         {
+        //             ctx.region.constrain_equal(h_out.cell(), h_in.cell())?;
+        //         }
+        //         // 3. Push the current h_out to assigned_last_hs_vec.
+        //         let mut new_assigned_hs_out = vec![];
+        //         for h_out in assigned_h_outs[0].iter() {
+        //             let assigned_on_gate = self.assigned_cell2value(ctx, h_out)?;
+        //             new_assigned_hs_out.push(assigned_on_gate)
+        //         }
+        //         assigned_last_hs_vec.push(new_assigned_hs_out);
+        //     }
+        // }
+
+        let assigned_input_bit_len = gate.mul(
+            ctx,
+            QuantumCell::Existing(&assigned_input_byte_size),
+            QuantumCell::Constant(F::from(8u64)),
+        );
+        let round_selectors = (0..assigned_last_state_vec.len())
+            .map(|n_round| {
+                gate.is_equal(
+                    ctx,
+                    QuantumCell::Constant(F::from(n_round as u64)),
+                    QuantumCell::Existing(&assigned_target_round),
+                )
+            })
+            .collect_vec();
+
+        // the padding must be 0x80 at the input length followed by zeros, both up to the length
+        // field of the target block and in every unused block after it.
+        let assigned_local_byte_size = gate.sub(
+            ctx,
+            QuantumCell::Existing(&assigned_input_byte_size),
+            QuantumCell::Constant(F::from(precomputed_input_len as u64)),
+        );
+        let mut is_padding = gate.load_zero(ctx);
+        for (idx, assigned_byte) in assigned_input_bytes.iter().enumerate() {
+            let is_terminator = gate.is_equal(
+                ctx,
+                QuantumCell::Constant(F::from(idx as u64)),
+                QuantumCell::Existing(&assigned_local_byte_size),
+            );
+            is_padding = gate.add(
+                ctx,
+                QuantumCell::Existing(&is_padding),
+                QuantumCell::Existing(&is_terminator),
+            );
+            // the length field of the target block is checked against the input length below.
+            let is_constrained = if idx % one_round_size >= one_round_size - 8 {
+                gate.sub(
+                    ctx,
+                    QuantumCell::Existing(&is_padding),
+                    QuantumCell::Existing(&round_selectors[idx / one_round_size + 1]),
+                )
+            } else {
+                is_padding.clone()
+            };
+            let byte_diff = gate.mul_add(
+                ctx,
+                QuantumCell::Existing(&is_terminator),
+                QuantumCell::Constant(-F::from(0x80u64)),
+                QuantumCell::Existing(assigned_byte),
+            );
+            let violation = gate.mul(
+                ctx,
+                QuantumCell::Existing(&is_constrained),
+                QuantumCell::Existing(&byte_diff),
+            );
+            gate.assert_is_const(ctx, &violation, F::zero());
+        }
+        // the input length must fall inside the assigned bytes, so exactly one terminator exists.
+        gate.assert_is_const(ctx, &is_padding, F::one());
+        // the length field must fit too, so the target is one of the hashed blocks; otherwise no
+        // state would be selected and the digest would be left all-zero.
+        let mut num_selected = gate.load_zero(ctx);
+        for selector in round_selectors.iter().skip(1) {
+            num_selected = gate.add(
+                ctx,
+                QuantumCell::Existing(&num_selected),
+                QuantumCell::Existing(selector),
+            );
+        }
+        gate.assert_is_const(ctx, &num_selected, F::one());
+
+        // the length field in the last hashed block must encode the input bit length.
+        let mut padded_bit_len = assigned_input_bit_len.clone();
+        let zero = gate.load_zero(ctx);
+        let mut output_h_out = vec![zero; 8];
+        for ((n_round, assigned_state), selector) in assigned_last_state_vec
+            .into_iter()
+            .enumerate()
+            .zip(round_selectors.into_iter())
+        {
+            for i in 0..8 {
+                output_h_out[i] = gate.select(
+                    ctx,
+                    QuantumCell::Existing(&assigned_state[i]),
+                    QuantumCell::Existing(&output_h_out[i]),
+                    QuantumCell::Existing(&selector),
+                )
+            }
+            if n_round > 0 {
+                let block_end = n_round * one_round_size;
+                let len_field =
+                    compose_be_bytes(ctx, gate, &assigned_input_bytes[(block_end - 8)..block_end]);
+                padded_bit_len = gate.select(
+                    ctx,
+                    QuantumCell::Existing(&len_field),
+                    QuantumCell::Existing(&padded_bit_len),
+                    QuantumCell::Existing(&selector),
+                );
+            }
+        }
+        gate.assert_equal(
+            ctx,
+            QuantumCell::Existing(&padded_bit_len),
+            QuantumCell::Existing(&assigned_input_bit_len),
+        );
+        let output_digest_bytes = state_to_be_bytes(ctx, &range, output_h_out.clone());
+        let result = AssignedHashResult {
+            input_len: assigned_input_byte_size,
+            input_bit_len: assigned_input_bit_len,
+            input_bytes: assigned_input_bytes,
+            output_bytes: output_digest_bytes,
+            output_words: output_h_out,
+            #[cfg(feature = "debug-schedule")]
+            message_schedules,
+        };
+        self.cur_hash_idx += 1;
+        Ok(result)
+    }
+
+    /// Packs a 32-byte digest into `(hi, lo)`, where `hi` is the big-endian integer of bytes
+    /// `0..16` and `lo` of bytes `16..32`. Both are below 2^128, so they can be absorbed by a
+    /// Poseidon chip over a ~254-bit field without reduction.
+    pub fn digest_to_field_pair_poseidon_ready<'b>(
+        &self,
+        ctx: &mut Context<'b, F>,
+        output_bytes: &[AssignedValue<'b, F>],
+    ) -> (AssignedValue<'b, F>, AssignedValue<'b, F>) {
+        assert_eq!(output_bytes.len(), 32);
+        let gate = self.range.gate();
+        let hi = compose_be_bytes(ctx, gate, &output_bytes[0..16]);
+        let lo = compose_be_bytes(ctx, gate, &output_bytes[16..32]);
+        (hi, lo)
+    }
+
+    /// Constrains the big-endian integer of the first `num_bytes` digest bytes to be strictly
+    /// less than `threshold`, e.g. a public proof-of-work difficulty target.
+    /// `threshold` is range checked to `8 * num_bytes` bits.
+    pub fn assert_digest_below<'b>(
+        &self,
+        ctx: &mut Context<'b, F>,
+        output_bytes: &[AssignedValue<'b, F>],
+        threshold: &AssignedValue<'b, F>,
+        num_bytes: usize,
+    ) {
+        assert_eq!(output_bytes.len(), 32);
+        // keep both operands far below the field modulus for `check_less_than`.
+        assert!(num_bytes > 0 && num_bytes <= 16);
+        let range = self.range();
+        let num_bits = 8 * num_bytes;
+        range.range_check(ctx, threshold, num_bits);
+        let prefix = compose_be_bytes(ctx, range.gate(), &output_bytes[0..num_bytes]);
+        range.check_less_than(
+            ctx,
+            QuantumCell::Existing(&prefix),
+            QuantumCell::Existing(threshold),
+            num_bits,
+        );
+    }
+
+    /// Hashes `input` and constrains the 8 big-endian digest words to equal `expected_words`,
+    /// e.g. cells assigned by another chip, instead of leaving the caller to copy them.
+    pub fn digest_to_words<'a, 'b: 'a>(
+        &'a mut self,
+        ctx: &mut Context<'b, F>,
+        input: &'a [u8],
+        expected_words: &[AssignedValue<'b, F>],
+    ) -> Result<AssignedHashResult<'b, F>, Error> {
+        assert_eq!(expected_words.len(), NUM_STATE_WORD);
+        let result = self.digest(ctx, input, None)?;
+        let gate = self.range.gate();
+        for (word, expected) in result.output_words.iter().zip(expected_words.iter()) {
+            gate.assert_equal(
+                ctx,
+                QuantumCell::Existing(word),
+                QuantumCell::Existing(expected),
+            );
+        }
+        Ok(result)
+    }
+
+    /// Returns an assigned boolean that is 1 iff `a` and `b` are equal byte by byte, without
+    /// constraining them to be equal.
+    pub fn digest_equals<'b>(
+        &self,
+        ctx: &mut Context<'b, F>,
+        a: &[AssignedValue<'b, F>],
+        b: &[AssignedValue<'b, F>],
+    ) -> AssignedValue<'b, F> {
+        assert_eq!(a.len(), b.len());
+        let gate = self.range.gate();
+        let mut all_equal = gate.load_constant(ctx, F::one());
+        for (a_byte, b_byte) in a.iter().zip(b.iter()) {
+            let is_equal = gate.is_equal(
+                ctx,
+                QuantumCell::Existing(a_byte),
+                QuantumCell::Existing(b_byte),
+            );
+            all_equal = gate.mul(
+                ctx,
+                QuantumCell::Existing(&all_equal),
+                QuantumCell::Existing(&is_equal),
+            );
+        }
+        all_equal
+    }
+
+    /// Packs the 32 digest bytes into four 64-bit lanes the way Keccak gadgets absorb bytes:
+    /// lane `i` is the little-endian integer of bytes `8 * i..8 * i + 8`.
+    pub fn digest_to_keccak_lanes<'b>(
+        &self,
+        ctx: &mut Context<'b, F>,
+        output_bytes: &[AssignedValue<'b, F>],
+    ) -> Vec<AssignedValue<'b, F>> {
+        assert_eq!(output_bytes.len(), 32);
+        let gate = self.range.gate();
+        output_bytes
+            .chunks(8)
+            .map(|lane_bytes| {
+                let be_bytes = lane_bytes.iter().rev().cloned().collect_vec();
+                compose_be_bytes(ctx, gate, &be_bytes)
+            })
+            .collect()
+    }
+
+    /// Loads a known digest as constant cells, e.g. to constrain a computed digest against a
+    /// hardcoded hash without making it a public input.
+    pub fn load_constant_digest<'b>(
+        &self,
+        ctx: &mut Context<'b, F>,
+        digest: &[u8; 32],
+    ) -> Vec<AssignedValue<'b, F>> {
+        let gate = self.range.gate();
+        digest
+            .iter()
+            .map(|byte| gate.load_constant(ctx, F::from(*byte as u64)))
+            .collect()
+    }
+
+    /// Clears the assignment state so the config can be reused in a fresh region or synthesis:
+    /// the next `digest` takes the first entry of `max_variable_byte_sizes`, its spread limbs
+    /// and digest set lookups start again at row 0, and the next `load` assigns the spread
+    /// table again. Call it before reusing a config from an earlier synthesis (e.g. keygen).
+    pub fn reset(&mut self) {
+        self.cur_hash_idx = 0;
+        self.spread_config.reset();
+        self.is_spread_table_loaded.set(false);
+        if let Some(digest_set) = self.digest_set.as_mut() {
+            digest_set.reset();
+        }
+    }
+
+    pub fn new_context<'a, 'b>(&'b self, region: Region<'a, F>) -> Context<'a, F> {
+        Context::new(
+            region,
+            ContextParams {
+                max_rows: self.range.gate.max_rows,
+                num_context_ids: 1,
+                fixed_columns: self.range.gate.constants.clone(),
+            },
+        )
+    }
+
+    /// **Unsound unless every input byte is range checked elsewhere.** With `trust = true`,
+    /// `digest` and every method built on it stop range checking the input bytes they assign,
+    /// so a malicious prover may hash arbitrary field elements instead of bytes. Only use it
+    /// when the caller constrains `AssignedHashResult::input_bytes` to cells that are already
+    /// known to be bytes, e.g. the output of a trusted upstream gadget.
+    ///
+    /// This duplicates the `is_input_range_check` flag of `configure`: both set the same field,
+    /// so `trust_input_bytes(trust)` overrides `is_input_range_check = !trust` for every later
+    /// digest. Use the configure flag for a whole circuit and this setter to toggle it around
+    /// individual digests.
+    pub fn trust_input_bytes(&mut self, trust: bool) {
+        self.is_input_range_check = !trust;
+    }
+
+    /// Replaces the 64 round constants `K` used by every digest and by `compress_block`, for
+    /// testing tweaked variants. The result is no longer SHA-256 unless `round_constants` is
+    /// `ROUND_CONSTANTS`, the default. `compress_block_no_feed_forward` always uses the
+    /// standard constants.
+    pub fn set_round_constants(&mut self, round_constants: [u32; NUM_ROUND]) {
+        self.round_constants = round_constants;
+    }
+
+    pub fn round_constants(&self) -> &[u32; NUM_ROUND] {
+        &self.round_constants
+    }
+
+    pub fn range(&self) -> &RangeConfig<F> {
+        &self.range
+    }
+
+    /// The gate of `range()`, for combining digest cells with custom arithmetic.
+    pub fn gate(&self) -> &FlexGateConfig<F> {
+        self.range.gate()
+    }
+
+    /// The advice columns holding the dense and the spread limbs, in that order. With a composite
+    /// spread table these hold the low limb of each pair.
+    pub fn spread_advice_columns(&self) -> (&[Column<Advice>], &[Column<Advice>]) {
+        (
+            self.spread_config.dense_columns(),
+            self.spread_config.spread_columns(),
+        )
+    }
+
+    /// The dense and spread columns of the spread lookup table. With a composite spread table
+    /// these are the columns of the low limb of each pair.
+    pub fn spread_table_columns(&self) -> (TableColumn, TableColumn) {
+        self.spread_config.table_columns()
+    }
+
+    /// Assigns the spread table. Calls after the first one are no-ops, so that several regions
+    /// (or chips) sharing this config can each make sure the table is loaded.
+    ///
+    /// The loaded flag is copied by `clone`, so the guarantee holds per clone: clone the config
+    /// after loading it, since a clone taken before would assign the table a second time. The
+    /// flag outlives the synthesis, so `reset` the config before reusing it in another one.
+    pub fn load(&self, layouter: &mut impl Layouter<F>) -> Result<(), Error> {
+        if self.is_spread_table_loaded.get() {
+            return Ok(());
+        }
+        #[cfg(feature = "tracing")]
+        let _span = tracing::info_span!("sha256_load_spread_table").entered();
+        self.spread_config.load(layouter)?;
+        self.is_spread_table_loaded.set(true);
+        Ok(())
+    }
+
+    /// Same as `load`, but assigns a spread table precomputed with
+    /// `SpreadTable::new(num_bits_lookup)`, which may be shared by many circuits.
+    pub fn load_with_spread_table(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        table: &SpreadTable<F>,
+    ) -> Result<(), Error> {
+        if self.is_spread_table_loaded.get() {
+            return Ok(());
+        }
+        self.spread_config.load_with_table(layouter, table)?;
+        self.is_spread_table_loaded.set(true);
+        Ok(())
+    }
+
+    /// The limbs `word` is split into for the spread lookups, with their spread values.
+    pub fn spread_decomposition(&self, word: u32) -> SpreadDecomposition {
+        self.spread_config.decomposition(word)
+    }
+
+    /// Checks that the spread table encodes `word` as the bit interleaving expected by the gadgets.
+    #[cfg(feature = "spread-self-test")]
+    pub fn spread_self_check(&self, word: u32) -> bool {
+        self.spread_config.self_check(word)
+    }
+}
+
+/// Constrains `cells` to the consecutive instance cells starting at `offset` in `column`.
+/// Binding `AssignedHashResult::input_bytes` this way makes the (padded) message public.
+pub fn constrain_cells_to_instance<F: PrimeField>(
+    layouter: &mut impl Layouter<F>,
+    cells: &[Cell],
+    column: Column<Instance>,
+    offset: usize,
+) -> Result<(), Error> {
+    for (idx, cell) in cells.iter().enumerate() {
+        layouter.constrain_instance(*cell, column, offset + idx)?;
+    }
+    Ok(())
+}
+
+/// Same as `constrain_cells_to_instance`, but binds `cells` to exactly the instance `rows` of
+/// `column`. Fails with `Error::Synthesis` if the lengths differ, which would otherwise leave
+/// some cells or instance rows silently unbound.
+pub fn constrain_cells_to_instance_rows<F: PrimeField>(
+    layouter: &mut impl Layouter<F>,
+    cells: &[Cell],
+    column: Column<Instance>,
+    rows: Range<usize>,
+) -> Result<(), Error> {
+    if cells.len() != rows.len() {
+        return Err(Error::Synthesis);
+    }
+    constrain_cells_to_instance(layouter, cells, column, rows.start)
+}
+
+fn state_to_be_bytes<'b, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    range: &RangeConfig<F>,
+    state_words: Vec<AssignedValue<'b, F>>,
+) -> Vec<AssignedValue<'b, F>> {
+    let gate = range.gate();
+    state_words
+        .into_iter()
+        .flat_map(|assigned_word| {
+            let be_bytes = assigned_word
+                .value()
+                .map(|v| v.get_lower_32().to_be_bytes().to_vec());
+            let assigned_bytes = (0..4)
+                .map(|idx| {
+                    let assigned =
+                        gate.load_witness(ctx, be_bytes.as_ref().map(|vs| F::from(vs[idx] as u64)));
+                    range.range_check(ctx, &assigned, 8);
+                    assigned
+                })
+                .collect::<Vec<AssignedValue<F>>>();
+            let mut sum = gate.load_zero(ctx);
+            for (idx, assigned_byte) in assigned_bytes.iter().enumerate() {
+                sum = gate.mul_add(
+                    ctx,
+                    QuantumCell::Existing(assigned_byte),
+                    QuantumCell::Constant(F::from(1u64 << (24 - 8 * idx))),
+                    QuantumCell::Existing(&sum),
+                );
+            }
+            gate.assert_equal(
+                ctx,
+                QuantumCell::Existing(&assigned_word),
+                QuantumCell::Existing(&sum),
+            );
+            assigned_bytes
+        })
+        .collect()
+}
+
+// Constrains the big-endian integer of the range-checked `bytes` to be less than `bound`.
+fn assert_be_bytes_less_than<'b, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    range: &RangeConfig<F>,
+    bytes: &[AssignedValue<'b, F>],
+    bound: &[u8],
+) {
+    assert_eq!(bytes.len(), bound.len());
+    let gate = range.gate();
+    let mut is_less = gate.load_zero(ctx);
+    let mut is_equal_prefix = gate.load_constant(ctx, F::one());
+    for (byte, bound_byte) in bytes.iter().zip(bound.iter()) {
+        let bound_byte = F::from(*bound_byte as u64);
+        let byte_is_less = range.is_less_than(
+            ctx,
+            QuantumCell::Existing(byte),
+            QuantumCell::Constant(bound_byte),
+            8,
+        );
+        let byte_is_equal = gate.is_equal(
+            ctx,
+            QuantumCell::Existing(byte),
+            QuantumCell::Constant(bound_byte),
+        );
+        is_less = gate.mul_add(
+            ctx,
+            QuantumCell::Existing(&is_equal_prefix),
+            QuantumCell::Existing(&byte_is_less),
+            QuantumCell::Existing(&is_less),
+        );
+        is_equal_prefix = gate.mul(
+            ctx,
+            QuantumCell::Existing(&is_equal_prefix),
+            QuantumCell::Existing(&byte_is_equal),
+        );
+    }
+    gate.assert_is_const(ctx, &is_less, F::one());
+}
+
+pub(crate) fn compose_be_bytes<'b, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    gate: &FlexGateConfig<F>,
+    bytes: &[AssignedValue<'b, F>],
+) -> AssignedValue<'b, F> {
+    let mut sum = gate.load_zero(ctx);
+    for byte in bytes.iter() {
+        sum = gate.mul_add(
+            ctx,
+            QuantumCell::Existing(&sum),
+            QuantumCell::Constant(F::from(1u64 << 8)),
+            QuantumCell::Existing(byte),
+        );
+    }
+    sum
+}
+
+#[cfg(test)]
+mod test {
+    use std::convert::TryFrom;
+    use std::marker::PhantomData;
+
+    use super::*;
+    use halo2_base::halo2_proofs::{
+        circuit::{Cell, Layouter, Region, SimpleFloorPlanner},
+        dev::MockProver,
+        halo2curves::bn256::Fr,
+        plonk::{Circuit, ConstraintSystem, Instance},
+    };
+    use halo2_base::{gates::range::RangeStrategy::Vertical, ContextParams, SKIP_FIRST_PASS};
+
+    use num_bigint::RandomBits;
+    use rand::rngs::OsRng;
+    use rand::{thread_rng, Rng};
+
+    #[derive(Debug, Clone)]
+    struct TestConfig<F: PrimeField> {
+        sha256: Sha256DynamicConfig<F>,
+        hash_column: Column<Instance>,
+    }
+
+    #[derive(Debug, Clone)]
+    struct TestCircuit<F: PrimeField> {
+        test_inputs: Vec<Vec<u8>>,
+        precomputed_input_lens: Vec<usize>,
+        _f: PhantomData<F>,
+    }
+
+    impl<F: PrimeField> Circuit<F> for TestCircuit<F> {
+        type Config = TestConfig<F>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            unimplemented!()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            let range_config = RangeConfig::configure(
+                meta,
+                Vertical,
+                &[Self::NUM_ADVICE],
+                &[Self::NUM_LOOKUP_ADVICE],
+                Self::NUM_FIXED,
+                Self::LOOKUP_BITS,
+                0,
+                17,
+            );
+            let hash_column = meta.instance_column();
+            meta.enable_equality(hash_column);
+            let sha256: Sha256DynamicConfig<F> = Sha256DynamicConfig::configure(
+                meta,
+                vec![Self::MAX_BYTE_SIZE1, Self::MAX_BYTE_SIZE2],
+                range_config,
+                8,
+                2,
+                true,
+            );
+            Self::Config {
+                sha256,
+                hash_column,
+            }
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            let mut sha256 = config.sha256.clone();
+            let range = sha256.range().clone();
+            sha256.range().load_lookup_table(&mut layouter)?;
+            sha256.load(&mut layouter)?;
+            let mut first_pass = SKIP_FIRST_PASS;
+            let mut assigned_hash_cells = vec![];
+            layouter.assign_region(
+                || "dynamic sha2 test",
+                |region| {
+                    if first_pass {
+                        first_pass = false;
+                        return Ok(());
+                    }
+
+                    let ctx = &mut sha256.new_context(region);
+                    let result0 = sha256.digest(
+                        ctx,
+                        &self.test_inputs[0],
+                        Some(self.precomputed_input_lens[0]),
+                    )?;
+                    assigned_hash_cells
+                        .append(&mut result0.output_bytes.into_iter().map(|v| v.cell()).collect());
+                    let result1 = sha256.digest(
+                        ctx,
+                        &self.test_inputs[1],
+                        Some(self.precomputed_input_lens[1]),
+                    )?;
+                    assigned_hash_cells
+                        .append(&mut result1.output_bytes.into_iter().map(|v| v.cell()).collect());
+                    range.finalize(ctx);
+                    {
+                        println!("total advice cells: {}", ctx.total_advice);
+                        let const_rows = ctx.total_fixed + 1;
+                        println!("maximum rows used by a fixed column: {const_rows}");
+                        println!("lookup cells used: {}", ctx.cells_to_lookup.len());
+                    }
+                    Ok(())
+                },
+            )?;
+            for (idx, hash) in assigned_hash_cells.into_iter().enumerate() {
+                layouter.constrain_instance(hash, config.hash_column, idx)?;
+            }
+            Ok(())
+        }
+    }
+
+    impl<F: PrimeField> TestCircuit<F> {
+        const MAX_BYTE_SIZE1: usize = 128;
+        const MAX_BYTE_SIZE2: usize = 128;
+        const NUM_ADVICE: usize = 3;
+        const NUM_FIXED: usize = 1;
+        const NUM_LOOKUP_ADVICE: usize = 1;
+        const LOOKUP_BITS: usize = 16;
+    }
+
+    type SynthFn = for<'v> fn(
+        &mut Sha256DynamicConfig<Fr>,
+        &mut Context<'v, Fr>,
+        &[Vec<u8>],
+    ) -> Result<Vec<AssignedValue<'v, Fr>>, Error>;
+
+    // Runs `synth` inside a single region and exposes the returned cells as public inputs.
+    #[derive(Clone)]
+    struct FnCircuit {
+        inputs: Vec<Vec<u8>>,
+        max_byte_sizes: Vec<usize>,
+        synth: SynthFn,
+    }
+
+    impl Circuit<Fr> for FnCircuit {
+        type Config = TestConfig<Fr>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            unimplemented!()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            let range_config = RangeConfig::configure(
+                meta,
+                Vertical,
+                &[Self::NUM_ADVICE],
+                &[Self::NUM_LOOKUP_ADVICE],
+                Self::NUM_FIXED,
+                Self::LOOKUP_BITS,
+                0,
+                Self::K as usize,
+            );
+            let hash_column = meta.instance_column();
+            meta.enable_equality(hash_column);
+            let sha256 = Sha256DynamicConfig::configure(
+                meta,
+                vec![],
+                range_config,
+                8,
+                Self::NUM_SPREAD_COLUMNS,
+                true,
+            );
+            TestConfig {
+                sha256,
+                hash_column,
+            }
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            let mut sha256 = config.sha256.clone();
+            sha256.max_variable_byte_sizes = self.max_byte_sizes.clone();
+            let range = sha256.range().clone();
+            range.load_lookup_table(&mut layouter)?;
+            sha256.load(&mut layouter)?;
+            let mut first_pass = SKIP_FIRST_PASS;
+            let mut public_cells = vec![];
+            layouter.assign_region(
+                || "sha256 fn circuit",
+                |region| {
+                    if first_pass {
+                        first_pass = false;
+                        return Ok(());
+                    }
+
+                    let ctx = &mut sha256.new_context(region);
+                    let public = (self.synth)(&mut sha256, ctx, &self.inputs)?;
+                    public_cells = public.iter().map(|v| v.cell()).collect();
+                    range.finalize(ctx);
+                    Ok(())
+                },
+            )?;
+            constrain_cells_to_instance(&mut layouter, &public_cells, config.hash_column, 0)
+        }
+    }
+
+    impl FnCircuit {
+        const K: u32 = 18;
+        const NUM_ADVICE: usize = 8;
+        const NUM_FIXED: usize = 1;
+        const NUM_LOOKUP_ADVICE: usize = 4;
+        const LOOKUP_BITS: usize = 16;
+        const NUM_SPREAD_COLUMNS: usize = 4;
+    }
+
+    fn mock_fn_circuit(
+        inputs: Vec<Vec<u8>>,
+        max_byte_sizes: Vec<usize>,
+        synth: SynthFn,
+        public: Vec<Fr>,
+    ) -> MockProver<Fr> {
+        let circuit = FnCircuit {
+            inputs,
+            max_byte_sizes,
+            synth,
+        };
+        MockProver::run(FnCircuit::K, &circuit, vec![public]).unwrap()
+    }
+
+    fn bytes_to_instances(bytes: &[u8]) -> Vec<Fr> {
+        bytes.iter().map(|byte| Fr::from(*byte as u64)).collect()
+    }
+
+    // Loads `bytes` as range-checked byte cells.
+    fn load_bytes<'v>(
+        ctx: &mut Context<'v, Fr>,
+        range: &RangeConfig<Fr>,
+        bytes: &[u8],
+    ) -> Vec<AssignedValue<'v, Fr>> {
+        bytes
+            .iter()
+            .map(|byte| {
+                let assigned = range
+                    .gate()
+                    .load_witness(ctx, Value::known(Fr::from(*byte as u64)));
+                range.range_check(ctx, &assigned, 8);
+                assigned
+            })
+            .collect_vec()
+    }
+
+    // Reference SHA-256 resuming from `state` after `precomputed_input_len` bytes.
+    fn sha256_from_state(
+        mut state: [u32; NUM_STATE_WORD],
+        input: &[u8],
+        precomputed_input_len: usize,
+    ) -> Vec<u8> {
+        let mut padded = input.to_vec();
+        padded.push(0x80);
+        while padded.len() % 64 != 56 {
+            padded.push(0);
+        }
+        padded.extend_from_slice(&(8 * (precomputed_input_len + input.len()) as u64).to_be_bytes());
+        let blocks = padded
+            .chunks(64)
+            .map(|bytes| GenericArray::clone_from_slice(bytes))
+            .collect_vec();
+        compress256(&mut state, &blocks[..]);
+        state.iter().flat_map(|word| word.to_be_bytes()).collect()
+    }
+
+    fn load_state<'v>(
+        sha256: &Sha256DynamicConfig<Fr>,
+        ctx: &mut Context<'v, Fr>,
+        state: &[u32; NUM_STATE_WORD],
+    ) -> Vec<AssignedValue<'v, Fr>> {
+        let gate = sha256.range().gate();
+        state
+            .iter()
+            .map(|word| gate.load_witness(ctx, Value::known(Fr::from(*word as u64))))
+            .collect()
+    }
+
+    #[test]
+    fn test_sha256_correct1() {
+        let k = 17;
+
+        // Test vector: "abc"
+        let test_input = vec!['a' as u8, 'b' as u8, 'c' as u8];
+
+        let circuit = TestCircuit::<Fr> {
+            test_inputs: vec![test_input, vec![]],
+            precomputed_input_lens: vec![0, 0],
+            _f: PhantomData,
+        };
+        let test_output0: [u8; 32] = [
+            0b10111010, 0b01111000, 0b00010110, 0b10111111, 0b10001111, 0b00000001, 0b11001111,
+            0b11101010, 0b01000001, 0b01000001, 0b01000000, 0b11011110, 0b01011101, 0b10101110,
+            0b00100010, 0b00100011, 0b10110000, 0b00000011, 0b01100001, 0b10100011, 0b10010110,
+            0b00010111, 0b01111010, 0b10011100, 0b10110100, 0b00010000, 0b11111111, 0b01100001,
+            0b11110010, 0b00000000, 0b00010101, 0b10101101,
+        ];
+        let test_output1 =
+            hex::decode("e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855")
+                .unwrap();
+        let test_output = vec![test_output0.to_vec(), test_output1]
+            .concat()
+            .into_iter()
+            .map(|val| Fr::from_u128(val as u128))
+            .collect();
+        let public_inputs = vec![test_output];
+
+        let prover = MockProver::run(k, &circuit, public_inputs).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_sha256_wrong_public_digest() {
+        let k = 17;
+        let test_inputs = vec![b"abc".to_vec(), vec![]];
+        let expected = test_inputs
+            .iter()
+            .flat_map(|input| Sha256::digest(input).to_vec())
+            .map(|val| Fr::from(val as u64))
+            .collect_vec();
+        let circuit = TestCircuit::<Fr> {
+            test_inputs,
+            precomputed_input_lens: vec![0, 0],
+            _f: PhantomData,
+        };
+        // first and last byte of each digest, so both hashes are bound to the instance column.
+        for idx in [0, 31, 32, 63] {
+            let mut wrong = expected.clone();
+            wrong[idx] += Fr::from(1u64);
+            let prover = MockProver::run(k, &circuit, vec![wrong]).unwrap();
+            assert!(prover.verify().is_err());
+        }
+    }
+
+    #[test]
+    fn test_sha256_swapped_public_digests() {
+        let k = 17;
+        let test_inputs = vec![b"abc".to_vec(), vec![]];
+        // each digest is bound to its own 32 instance cells, so swapping them is rejected.
+        let swapped = test_inputs
+            .iter()
+            .rev()
+            .flat_map(|input| Sha256::digest(input).to_vec())
+            .map(|val| Fr::from(val as u64))
+            .collect_vec();
+        let circuit = TestCircuit::<Fr> {
+            test_inputs,
+            precomputed_input_lens: vec![0, 0],
+            _f: PhantomData,
+        };
+        let prover = MockProver::run(k, &circuit, vec![swapped]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_sha256_correct2() {
+        let k = 17;
+
+        // Test vector: "0x0"
+        let test_input = vec![0u8];
+
+        let circuit = TestCircuit::<Fr> {
+            test_inputs: vec![test_input, vec![]],
+            precomputed_input_lens: vec![0, 0],
+            _f: PhantomData,
+        };
+        let test_output0 =
+            hex::decode("6e340b9cffb37a989ca544e6bb780a2c78901d3fb33738768511a30617afa01d")
+                .unwrap();
+        let test_output1 =
+            hex::decode("e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855")
+                .unwrap();
+        let test_output = vec![test_output0, test_output1]
+            .concat()
+            .into_iter()
+            .map(|val| Fr::from_u128(val as u128))
+            .collect();
+        let public_inputs = vec![test_output];
+
+        let prover = MockProver::run(k, &circuit, public_inputs).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_sha256_correct3() {
+        let k = 17;
+
+        let test_inputs = vec![vec![0x1; 56], vec![0u8, 0u8, 0u8]];
+
+        let circuit = TestCircuit::<Fr> {
+            test_inputs,
+            precomputed_input_lens: vec![0, 0],
+            _f: PhantomData,
+        };
+        let test_output0 =
+            hex::decode("51e14a913680f24c85fe3b0e2e5b57f7202f117bb214f8ffdd4ea0f4e921fd52")
+                .unwrap();
+        let test_output1 =
+            hex::decode("709e80c88487a2411e1ee4dfb9f22a861492d20c4765150c0c794abd70f8147c")
+                .unwrap();
+        let test_output = vec![test_output0, test_output1]
+            .concat()
+            .into_iter()
+            .map(|val| Fr::from_u128(val as u128))
+            .collect();
+        let public_inputs = vec![test_output];
+
+        let prover = MockProver::run(k, &circuit, public_inputs).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_sha256_correct4() {
+        let k = 17;
+
+        fn gen_random_bytes(len: usize) -> Vec<u8> {
+            let mut rng = thread_rng();
+            (0..len).map(|_| rng.gen::<u8>()).collect()
+        }
+        let test_inputs = vec![gen_random_bytes(128 + 64), gen_random_bytes(128 + 64)];
+        let test_output0 = Sha256::digest(&test_inputs[0]);
+        let test_output1 = Sha256::digest(&test_inputs[1]);
+        let circuit = TestCircuit::<Fr> {
+            test_inputs,
+            precomputed_input_lens: vec![128, 128],
+            _f: PhantomData,
+        };
+        let test_output = vec![test_output0, test_output1]
+            .concat()
+            .into_iter()
+            .map(|val| Fr::from_u128(val as u128))
+            .collect();
+        let public_inputs = vec![test_output];
+
+        let prover = MockProver::run(k, &circuit, public_inputs).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[cfg(feature = "spread-self-test")]
+    #[test]
+    fn test_spread_self_check() {
+        let mut meta = ConstraintSystem::<Fr>::default();
+        let config = TestCircuit::<Fr>::configure(&mut meta);
+        let mut rng = thread_rng();
+        for word in [0u32, 1, 0x8000_0000, u32::MAX] {
+            assert!(config.sha256.spread_self_check(word));
+        }
+        for _ in 0..1000 {
+            assert!(config.sha256.spread_self_check(rng.gen()));
+        }
+    }
+
+    #[test]
+    fn test_digest_with_iv_standard() {
+        fn synth<'v>(
+            sha256: &mut Sha256DynamicConfig<Fr>,
+            ctx: &mut Context<'v, Fr>,
+            inputs: &[Vec<u8>],
+        ) -> Result<Vec<AssignedValue<'v, Fr>>, Error> {
+            let iv = load_state(sha256, ctx, &INIT_STATE);
+            let result = sha256.digest_with_iv(ctx, &inputs[0], &iv, 0)?;
+            Ok(result.output_bytes)
+        }
+        let input = b"abc".to_vec();
+        let expected = Sha256::digest(&input);
+        let prover = mock_fn_circuit(vec![input], vec![64], synth, bytes_to_instances(&expected));
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_digest_with_iv_midstate() {
+        fn synth<'v>(
+            sha256: &mut Sha256DynamicConfig<Fr>,
+            ctx: &mut Context<'v, Fr>,
+            inputs: &[Vec<u8>],
+        ) -> Result<Vec<AssignedValue<'v, Fr>>, Error> {
+            let mut midstate = INIT_STATE;
+            compress256(
+                &mut midstate,
+                &[GenericArray::clone_from_slice(&inputs[0][0..64])],
+            );
+            let iv = load_state(sha256, ctx, &midstate);
+            let result = sha256.digest_with_iv(ctx, &inputs[0][64..], &iv, 64)?;
+            Ok(result.output_bytes)
+        }
+        let input = (0..100).map(|idx| idx as u8).collect_vec();
+        let expected = Sha256::digest(&input);
+        let prover = mock_fn_circuit(vec![input], vec![128], synth, bytes_to_instances(&expected));
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_digest_with_iv_custom() {
+        const CUSTOM_IV: [u32; NUM_STATE_WORD] = [
+            0x0123_4567,
+            0x89ab_cdef,
+            0xfedc_ba98,
+            0x7654_3210,
+            0x0f1e_2d3c,
+            0x4b5a_6978,
+            0x8796_a5b4,
+            0xc3d2_e1f0,
+        ];
+        fn synth<'v>(
+            sha256: &mut Sha256DynamicConfig<Fr>,
+            ctx: &mut Context<'v, Fr>,
+            inputs: &[Vec<u8>],
+        ) -> Result<Vec<AssignedValue<'v, Fr>>, Error> {
+            let iv = load_state(sha256, ctx, &CUSTOM_IV);
+            let result = sha256.digest_with_iv(ctx, &inputs[0], &iv, 0)?;
+            Ok(result.output_bytes)
+        }
+        let input = b"abc".to_vec();
+        let expected = sha256_from_state(CUSTOM_IV, &input, 0);
+        assert_ne!(expected, Sha256::digest(&input).to_vec());
+        let prover = mock_fn_circuit(vec![input], vec![64], synth, bytes_to_instances(&expected));
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_digest_to_field_pair_poseidon_ready() {
+        fn synth<'v>(
+            sha256: &mut Sha256DynamicConfig<Fr>,
+            ctx: &mut Context<'v, Fr>,
+            inputs: &[Vec<u8>],
+        ) -> Result<Vec<AssignedValue<'v, Fr>>, Error> {
+            let result = sha256.digest(ctx, &inputs[0], None)?;
+            let (hi, lo) = sha256.digest_to_field_pair_poseidon_ready(ctx, &result.output_bytes);
+            Ok(vec![hi, lo])
+        }
+        let input = b"abc".to_vec();
+        let expected = Sha256::digest(&input);
+        let hi = u128::from_be_bytes(expected[0..16].try_into().unwrap());
+        let lo = u128::from_be_bytes(expected[16..32].try_into().unwrap());
+        assert_eq!(hi, 0xba7816bf8f01cfea414140de5dae2223);
+        let public = vec![Fr::from_u128(hi), Fr::from_u128(lo)];
+        let prover = mock_fn_circuit(vec![input], vec![64], synth, public);
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    const SET_MEMBER_INPUT: &[u8] = b"abc";
+
+    fn sha256_array(input: &[u8]) -> [u8; 32] {
+        let mut digest = [0; 32];
+        digest.copy_from_slice(&Sha256::digest(input));
+        digest
+    }
+
+    fn other_digests() -> Vec<[u8; 32]> {
+        vec![sha256_array(b"abd"), sha256_array(b"")]
+    }
+
+    // Hashes `input` and constrains the digest to be in the lookup table of `allowed` digests.
+    #[derive(Clone)]
+    struct DigestSetCircuit {
+        input: Vec<u8>,
+        allowed: Vec<[u8; 32]>,
+    }
+
+    impl Circuit<Fr> for DigestSetCircuit {
+        type Config = TestConfig<Fr>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            self.clone()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            let mut config = FnCircuit::configure(meta);
+            config.sha256.configure_digest_set(meta);
+            config
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            let mut sha256 = config.sha256.clone();
+            sha256.max_variable_byte_sizes = vec![64];
+            let range = sha256.range().clone();
+            range.load_lookup_table(&mut layouter)?;
+            sha256.load(&mut layouter)?;
+            sha256.load_digest_set(&mut layouter, &self.allowed)?;
+            let mut first_pass = SKIP_FIRST_PASS;
+            layouter.assign_region(
+                || "sha256 digest set",
+                |region| {
+                    if first_pass {
+                        first_pass = false;
+                        return Ok(());
+                    }
+                    let ctx = &mut sha256.new_context(region);
+                    let result = sha256.digest(ctx, &self.input, None)?;
+                    sha256.assert_digest_in_set(ctx, &result.output_bytes)?;
+                    range.finalize(ctx);
+                    Ok(())
+                },
+            )
+        }
+    }
+
+    #[test]
+    fn test_assert_digest_in_set() {
+        let mut allowed = other_digests();
+        allowed.push(sha256_array(SET_MEMBER_INPUT));
+        let circuit = DigestSetCircuit {
+            input: SET_MEMBER_INPUT.to_vec(),
+            allowed,
+        };
+        let prover = MockProver::run(FnCircuit::K, &circuit, vec![vec![]]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_assert_digest_not_in_set() {
+        let circuit = DigestSetCircuit {
+            input: SET_MEMBER_INPUT.to_vec(),
+            allowed: other_digests(),
+        };
+        let prover = MockProver::run(FnCircuit::K, &circuit, vec![vec![]]).unwrap();
+        assert!(prover.verify().is_err());
+
+        // an empty set admits no digest, although the table still holds its padding row.
+        let circuit = DigestSetCircuit {
+            input: SET_MEMBER_INPUT.to_vec(),
+            allowed: vec![],
+        };
+        let prover = MockProver::run(FnCircuit::K, &circuit, vec![vec![]]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    fn synth_digest_all<'v>(
+        sha256: &mut Sha256DynamicConfig<Fr>,
+        ctx: &mut Context<'v, Fr>,
+        inputs: &[Vec<u8>],
+    ) -> Result<Vec<AssignedValue<'v, Fr>>, Error> {
+        let mut outputs = vec![];
+        for input in inputs.iter() {
+            outputs.append(&mut sha256.digest(ctx, input, None)?.output_bytes);
+        }
+        Ok(outputs)
+    }
+
+    #[test]
+    fn test_sha256_padding_edge_cases() {
+        // 55 bytes is the longest input fitting one block; 56..=64 spill the length into a second block.
+        let inputs = [55, 56, 63, 64]
+            .iter()
+            .map(|len| (0..*len).map(|idx| (idx * 7) as u8).collect_vec())
+            .collect_vec();
+        let expected = inputs
+            .iter()
+            .flat_map(|input| Sha256::digest(input).to_vec())
+            .collect_vec();
+        let prover = mock_fn_circuit(
+            inputs,
+            vec![128; 4],
+            synth_digest_all,
+            bytes_to_instances(&expected),
+        );
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_input_bit_len() {
+        fn synth<'v>(
+            sha256: &mut Sha256DynamicConfig<Fr>,
+            ctx: &mut Context<'v, Fr>,
+            inputs: &[Vec<u8>],
+        ) -> Result<Vec<AssignedValue<'v, Fr>>, Error> {
+            let result = sha256.digest(ctx, &inputs[0], None)?;
+            Ok(vec![result.input_len, result.input_bit_len])
+        }
+        let input = vec![0x5a; 70];
+        let public = vec![Fr::from(70), Fr::from(8 * 70)];
+        let prover = mock_fn_circuit(vec![input.clone()], vec![192], synth, public);
+        assert_eq!(prover.verify(), Ok(()));
+
+        let public = vec![Fr::from(70), Fr::from(70)];
+        let prover = mock_fn_circuit(vec![input], vec![192], synth, public);
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_digest_mixed_public_prefix() {
+        fn synth<'v>(
+            sha256: &mut Sha256DynamicConfig<Fr>,
+            ctx: &mut Context<'v, Fr>,
+            inputs: &[Vec<u8>],
+        ) -> Result<Vec<AssignedValue<'v, Fr>>, Error> {
+            let public_mask = (0..inputs[0].len()).map(|idx| idx < 4).collect_vec();
+            let (result, mut public_bytes) = sha256.digest_mixed(ctx, &inputs[0], &public_mask)?;
+            let mut public = result.output_bytes;
+            public.append(&mut public_bytes);
+            Ok(public)
+        }
+        let input = b"head:secret body".to_vec();
+        let expected = [Sha256::digest(&input).to_vec(), input[0..4].to_vec()].concat();
+        let prover = mock_fn_circuit(
+            vec![input.clone()],
+            vec![64],
+            synth,
+            bytes_to_instances(&expected),
+        );
+        assert_eq!(prover.verify(), Ok(()));
+
+        let wrong_prefix = [Sha256::digest(&input).to_vec(), b"tail".to_vec()].concat();
+        let prover = mock_fn_circuit(
+            vec![input],
+            vec![64],
+            synth,
+            bytes_to_instances(&wrong_prefix),
+        );
+        assert!(prover.verify().is_err());
+    }
+
+    fn synth_constant_abc_digest<'v>(
+        sha256: &mut Sha256DynamicConfig<Fr>,
+        ctx: &mut Context<'v, Fr>,
+        inputs: &[Vec<u8>],
+    ) -> Result<Vec<AssignedValue<'v, Fr>>, Error> {
+        let result = sha256.digest(ctx, &inputs[0], None)?;
+        let expected = sha256.load_constant_digest(ctx, &sha256_array(b"abc"));
+        let gate = sha256.range().gate();
+        for (computed, expected) in result.output_bytes.iter().zip(expected.iter()) {
+            gate.assert_equal(
+                ctx,
+                QuantumCell::Existing(computed),
+                QuantumCell::Existing(expected),
+            );
+        }
+        Ok(vec![])
+    }
+
+    #[test]
+    fn test_load_constant_digest() {
+        let prover = mock_fn_circuit(
+            vec![b"abc".to_vec()],
+            vec![64],
+            synth_constant_abc_digest,
+            vec![],
+        );
+        assert_eq!(prover.verify(), Ok(()));
+
+        let prover = mock_fn_circuit(
+            vec![b"abd".to_vec()],
+            vec![64],
+            synth_constant_abc_digest,
+            vec![],
+        );
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_digest_tagged() {
+        fn synth<'v>(
+            sha256: &mut Sha256DynamicConfig<Fr>,
+            ctx: &mut Context<'v, Fr>,
+            inputs: &[Vec<u8>],
+        ) -> Result<Vec<AssignedValue<'v, Fr>>, Error> {
+            let result = sha256.digest_tagged(ctx, b"my-protocol/v1", &inputs[0])?;
+            Ok(result.output_bytes)
+        }
+        let msg = b"hello".to_vec();
+        let expected = Sha256::digest(&[b"my-protocol/v1".to_vec(), msg.clone()].concat());
+        let prover = mock_fn_circuit(
+            vec![msg.clone()],
+            vec![64],
+            synth,
+            bytes_to_instances(&expected),
+        );
+        assert_eq!(prover.verify(), Ok(()));
+
+        // the tag is fixed by the circuit, so a digest under another tag is rejected.
+        let other_tag = Sha256::digest(&[b"my-protocol/v2".to_vec(), msg.clone()].concat());
+        let prover = mock_fn_circuit(vec![msg], vec![64], synth, bytes_to_instances(&other_tag));
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_digest_bip340_tagged() {
+        fn synth<'v>(
+            sha256: &mut Sha256DynamicConfig<Fr>,
+            ctx: &mut Context<'v, Fr>,
+            inputs: &[Vec<u8>],
+        ) -> Result<Vec<AssignedValue<'v, Fr>>, Error> {
+            let result = sha256.digest_bip340_tagged(ctx, b"BIP0340/challenge", &inputs[0])?;
+            Ok(result.output_bytes)
+        }
+        let msg = vec![0x42; 96];
+        let tag_hash = Sha256::digest(b"BIP0340/challenge");
+        let mut hasher = Sha256::new();
+        hasher.update(tag_hash);
+        hasher.update(tag_hash);
+        hasher.update(&msg);
+        let expected = hasher.finalize();
+        let prover = mock_fn_circuit(vec![msg], vec![192], synth, bytes_to_instances(&expected));
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[cfg(feature = "debug-schedule")]
+    #[test]
+    fn test_message_schedule() {
+        fn synth<'v>(
+            sha256: &mut Sha256DynamicConfig<Fr>,
+            ctx: &mut Context<'v, Fr>,
+            inputs: &[Vec<u8>],
+        ) -> Result<Vec<AssignedValue<'v, Fr>>, Error> {
+            let mut result = sha256.digest(ctx, &inputs[0], None)?;
+            Ok(result.message_schedules.remove(0))
+        }
+        // padded single block of "abc".
+        let mut block = b"abc".to_vec();
+        block.push(0x80);
+        block.resize(56, 0);
+        block.extend_from_slice(&24u64.to_be_bytes());
+        let mut w = block
+            .chunks(4)
+            .map(|bytes| u32::from_be_bytes(bytes.try_into().unwrap()))
+            .collect_vec();
+        for idx in 16..64 {
+            let s0 =
+                w[idx - 15].rotate_right(7) ^ w[idx - 15].rotate_right(18) ^ (w[idx - 15] >> 3);
+            let s1 = w[idx - 2].rotate_right(17) ^ w[idx - 2].rotate_right(19) ^ (w[idx - 2] >> 10);
+            w.push(
+                w[idx - 16]
+                    .wrapping_add(s0)
+                    .wrapping_add(w[idx - 7])
+                    .wrapping_add(s1),
+            );
+        }
+        assert_eq!(w[16], 0x61626380);
+        let public = w.iter().map(|word| Fr::from(*word as u64)).collect();
+        let prover = mock_fn_circuit(vec![b"abc".to_vec()], vec![64], synth, public);
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_num_blocks_for_len() {
+        for len in 0..200 {
+            let mut padded_len = len + 1;
+            while padded_len % 64 != 56 {
+                padded_len += 1;
+            }
+            assert_eq!(
+                num_blocks_for_len(len),
+                (padded_len + 8) / 64,
+                "len {}",
+                len
+            );
+        }
+        assert_eq!(num_blocks_for_len(55), 1);
+        assert_eq!(num_blocks_for_len(56), 2);
+        assert_eq!(num_blocks_for_len(64), 2);
+        assert_eq!(num_blocks_for_len(120), 3);
+    }
+
+    #[test]
+    fn test_digest_public_input() {
+        fn synth<'v>(
+            sha256: &mut Sha256DynamicConfig<Fr>,
+            ctx: &mut Context<'v, Fr>,
+            inputs: &[Vec<u8>],
+        ) -> Result<Vec<AssignedValue<'v, Fr>>, Error> {
+            let result = sha256.digest(ctx, &inputs[0], None)?;
+            Ok([result.input_bytes, result.output_bytes].concat())
+        }
+        let input = b"public statement".to_vec();
+        let mut padded = input.clone();
+        padded.push(0x80);
+        padded.resize(56, 0);
+        padded.extend_from_slice(&(8 * input.len() as u64).to_be_bytes());
+        let public = [padded, Sha256::digest(&input).to_vec()].concat();
+        let prover = mock_fn_circuit(vec![input], vec![64], synth, bytes_to_instances(&public));
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn test_tracing_spans() {
+        use std::sync::atomic::{AtomicU64, Ordering};
+        use std::sync::{Arc, Mutex};
+        use tracing::{span, Event, Metadata, Subscriber};
+
+        struct SpanRecorder {
+            names: Arc<Mutex<Vec<&'static str>>>,
+            next_id: AtomicU64,
+        }
+
+        impl Subscriber for SpanRecorder {
+            fn enabled(&self, _: &Metadata<'_>) -> bool {
+                true
+            }
+            fn new_span(&self, span: &span::Attributes<'_>) -> span::Id {
+                self.names.lock().unwrap().push(span.metadata().name());
+                span::Id::from_u64(self.next_id.fetch_add(1, Ordering::Relaxed))
+            }
+            fn record(&self, _: &span::Id, _: &span::Record<'_>) {}
+            fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+            fn event(&self, _: &Event<'_>) {}
+            fn enter(&self, _: &span::Id) {}
+            fn exit(&self, _: &span::Id) {}
+        }
+
+        let names = Arc::new(Mutex::new(vec![]));
+        let recorder = SpanRecorder {
+            names: names.clone(),
+            next_id: AtomicU64::new(1),
+        };
+        tracing::subscriber::with_default(recorder, || {
+            let prover = mock_fn_circuit(
+                vec![b"abc".to_vec()],
+                vec![128],
+                synth_digest_all,
+                bytes_to_instances(&Sha256::digest(b"abc")),
+            );
+            assert_eq!(prover.verify(), Ok(()));
+        });
+        let names = names.lock().unwrap();
+        assert!(names.contains(&"sha256_load_spread_table"));
+        assert_eq!(
+            names
+                .iter()
+                .filter(|name| **name == "sha256_digest")
+                .count(),
+            1
+        );
+        assert_eq!(
+            names
+                .iter()
+                .filter(|name| **name == "sha256_compression")
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_digest_block() {
+        fn synth<'v>(
+            sha256: &mut Sha256DynamicConfig<Fr>,
+            ctx: &mut Context<'v, Fr>,
+            inputs: &[Vec<u8>],
+        ) -> Result<Vec<AssignedValue<'v, Fr>>, Error> {
+            let result = sha256.digest(ctx, &inputs[0], None)?;
+            let (left, right) = result.input_bytes[0..64].split_at(32);
+            let block_digest = sha256.digest_block(ctx, &Block::from_halves(left, right))?;
+            Ok([result.output_bytes, block_digest].concat())
+        }
+        let input = (0..64).map(|idx| (255 - idx) as u8).collect_vec();
+        let expected = Sha256::digest(&input).to_vec();
+        let public = bytes_to_instances(&[expected.clone(), expected].concat());
+        let prover = mock_fn_circuit(vec![input], vec![128], synth, public);
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    // Four leaves `sha256(b"a")..sha256(b"d")`, proving leaf 2. `inputs` holds the leaf preimage,
+    // the two siblings and the root, which is exposed as the public input.
+    const MERKLE_LEAF_INDEX: usize = 2;
+
+    fn merkle_test_inputs() -> Vec<Vec<u8>> {
+        let leaves = [b"a", b"b", b"c", b"d"]
+            .iter()
+            .map(|data| Sha256::digest(*data).to_vec())
+            .collect_vec();
+        let node01 = Sha256::digest(&[leaves[0].clone(), leaves[1].clone()].concat()).to_vec();
+        let node23 = Sha256::digest(&[leaves[2].clone(), leaves[3].clone()].concat()).to_vec();
+        let root = Sha256::digest(&[node01.clone(), node23].concat()).to_vec();
+        vec![b"c".to_vec(), leaves[3].clone(), node01, root]
+    }
+
+    fn synth_merkle_path<'v>(
+        sha256: &mut Sha256DynamicConfig<Fr>,
+        ctx: &mut Context<'v, Fr>,
+        inputs: &[Vec<u8>],
+    ) -> Result<Vec<AssignedValue<'v, Fr>>, Error> {
+        let leaf = sha256.digest(ctx, &inputs[0], None)?.output_bytes;
+        let range = sha256.range().clone();
+        let gate = range.gate();
+        let path = vec![
+            load_bytes(ctx, &range, &inputs[1]),
+            load_bytes(ctx, &range, &inputs[2]),
+        ];
+        let root = load_bytes(ctx, &range, &inputs[3]);
+        let index_bits = (0..path.len())
+            .map(|idx| {
+                let bit = (MERKLE_LEAF_INDEX >> idx) & 1;
+                gate.load_witness(ctx, Value::known(Fr::from(bit as u64)))
+            })
+            .collect_vec();
+        sha256.verify_merkle_path(ctx, &leaf, &path, &index_bits, &root)?;
+        Ok(root)
+    }
+
+    #[test]
+    fn test_verify_merkle_path() {
+        let inputs = merkle_test_inputs();
+        let public = bytes_to_instances(&inputs[3]);
+        let prover = mock_fn_circuit(inputs, vec![64], synth_merkle_path, public);
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_verify_merkle_path_tampered() {
+        let mut inputs = merkle_test_inputs();
+        inputs[2][0] ^= 1;
+        let public = bytes_to_instances(&inputs[3]);
+        let prover = mock_fn_circuit(inputs, vec![64], synth_merkle_path, public);
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_digest_stats() {
+        fn synth<'v>(
+            sha256: &mut Sha256DynamicConfig<Fr>,
+            ctx: &mut Context<'v, Fr>,
+            inputs: &[Vec<u8>],
+        ) -> Result<Vec<AssignedValue<'v, Fr>>, Error> {
+            let (_, abc_stats) = sha256.digest_with_stats(ctx, &inputs[0], None)?;
+            let (_, other_stats) = sha256.digest_with_stats(ctx, &inputs[1], None)?;
+            let (_, two_block_stats) = sha256.digest_with_stats(ctx, &inputs[2], None)?;
+            // the circuit shape must not depend on the private input length.
+            assert_eq!(abc_stats, other_stats);
+            // spread lookups only come from the block compressions.
+            assert_eq!(two_block_stats.spread_lookups, 2 * abc_stats.spread_lookups);
+            assert!(two_block_stats.advice_cells > abc_stats.advice_cells);
+            assert!(two_block_stats.range_lookup_cells > abc_stats.range_lookup_cells);
+            Ok(vec![])
+        }
+        let inputs = vec![b"abc".to_vec(), vec![0x5a; 55], b"abc".to_vec()];
+        let prover = mock_fn_circuit(inputs, vec![64, 64, 128], synth, vec![]);
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_digest_u32_u64() {
+        const X_U32: u32 = 0xdead_beef;
+        const X_U64: u64 = 0x0123_4567_89ab_cdef;
+        fn synth<'v>(
+            sha256: &mut Sha256DynamicConfig<Fr>,
+            ctx: &mut Context<'v, Fr>,
+            _: &[Vec<u8>],
+        ) -> Result<Vec<AssignedValue<'v, Fr>>, Error> {
+            let gate = sha256.range().gate().clone();
+            let x_u32 = gate.load_witness(ctx, Value::known(Fr::from(X_U32 as u64)));
+            let x_u64 = gate.load_witness(ctx, Value::known(Fr::from(X_U64)));
+            let result_u32 = sha256.digest_u32(ctx, &x_u32)?;
+            let result_u64 = sha256.digest_u64(ctx, &x_u64)?;
+            Ok([result_u32.output_bytes, result_u64.output_bytes].concat())
+        }
+        let expected = [
+            Sha256::digest(&X_U32.to_be_bytes()).to_vec(),
+            Sha256::digest(&X_U64.to_be_bytes()).to_vec(),
+        ]
+        .concat();
+        let prover = mock_fn_circuit(vec![], vec![64, 64], synth, bytes_to_instances(&expected));
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_digest_length_extension() {
+        const ORIGINAL: &[u8] = b"secret key || amount=100";
+        fn synth<'v>(
+            sha256: &mut Sha256DynamicConfig<Fr>,
+            ctx: &mut Context<'v, Fr>,
+            inputs: &[Vec<u8>],
+        ) -> Result<Vec<AssignedValue<'v, Fr>>, Error> {
+            let digest = load_bytes(ctx, sha256.range(), &inputs[0]);
+            let result =
+                sha256.digest_length_extension(ctx, &digest, ORIGINAL.len(), &inputs[1])?;
+            Ok(result.output_bytes)
+        }
+        let extension = b"&amount=1000000".to_vec();
+        let mut forged = ORIGINAL.to_vec();
+        forged.push(0x80);
+        while forged.len() % 64 != 56 {
+            forged.push(0);
+        }
+        forged.extend_from_slice(&(8 * ORIGINAL.len() as u64).to_be_bytes());
+        forged.extend_from_slice(&extension);
+        let expected = Sha256::digest(&forged);
+        let inputs = vec![Sha256::digest(ORIGINAL).to_vec(), extension];
+        let prover = mock_fn_circuit(inputs, vec![64], synth, bytes_to_instances(&expected));
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    fn block_words(block: &[u8]) -> [u32; 16] {
+        let mut words = [0u32; 16];
+        for (word, bytes) in words.iter_mut().zip(block.chunks(4)) {
+            *word = u32::from_be_bytes(bytes.try_into().unwrap());
+        }
+        words
+    }
+
+    #[test]
+    fn test_compress_block_nist() {
+        // FIPS 180-2 example "abc": the single padded block.
+        let mut abc_block = vec![0u8; 64];
+        abc_block[0..3].copy_from_slice(b"abc");
+        abc_block[3] = 0x80;
+        abc_block[63] = 24;
+        assert_eq!(
+            compress_block(INIT_STATE, block_words(&abc_block)),
+            [
+                0xba7816bf, 0x8f01cfea, 0x414140de, 0x5dae2223, 0xb00361a3, 0x96177a9c, 0xb410ff61,
+                0xf20015ad,
+            ]
+        );
+        // FIPS 180-2 two-block example: the intermediate hash value H(1).
+        let message = b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq";
+        let mut first_block = message.to_vec();
+        first_block.push(0x80);
+        first_block.resize(64, 0);
+        assert_eq!(
+            compress_block(INIT_STATE, block_words(&first_block)),
+            [
+                0x85e655d6, 0x417a1795, 0x3363376a, 0x624cde5c, 0x76e09589, 0xcac5f811, 0xcc4b32c1,
+                0xf20e533a,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_compress_block_matches_sha2() {
+        let mut rng = thread_rng();
+        for _ in 0..100 {
+            let block = (0..64).map(|_| rng.gen::<u8>()).collect_vec();
+            let mut state = INIT_STATE;
+            for word in state.iter_mut() {
+                *word = rng.gen();
+            }
+            let mut expected = state;
+            compress256(&mut expected, &[GenericArray::clone_from_slice(&block)]);
+            assert_eq!(compress_block(state, block_words(&block)), expected);
+        }
+    }
+
+    #[test]
+    fn test_compress_block_in_circuit() {
+        fn synth<'v>(
+            sha256: &mut Sha256DynamicConfig<Fr>,
+            ctx: &mut Context<'v, Fr>,
+            inputs: &[Vec<u8>],
+        ) -> Result<Vec<AssignedValue<'v, Fr>>, Error> {
+            let state = load_state(sha256, ctx, &INIT_STATE);
+            let block = load_bytes(ctx, sha256.range(), &inputs[0]);
+            sha256.compress_block(ctx, &state, &block)
+        }
+        let block = (0..64).map(|idx| (3 * idx) as u8).collect_vec();
+        let expected = compress_block(INIT_STATE, block_words(&block))
+            .iter()
+            .map(|word| Fr::from(*word as u64))
+            .collect_vec();
+        let prover = mock_fn_circuit(vec![block], vec![], synth, expected);
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_digest_nibbles() {
+        fn synth<'v>(
+            sha256: &mut Sha256DynamicConfig<Fr>,
+            ctx: &mut Context<'v, Fr>,
+            inputs: &[Vec<u8>],
+        ) -> Result<Vec<AssignedValue<'v, Fr>>, Error> {
+            let gate = sha256.range().gate().clone();
+            let nibbles = inputs[0]
+                .iter()
+                .map(|nibble| gate.load_witness(ctx, Value::known(Fr::from(*nibble as u64))))
+                .collect_vec();
+            let result = sha256.digest_nibbles(ctx, &nibbles)?;
+            Ok(result.output_bytes)
+        }
+        let input = b"nibble-encoded message \x00\xff".to_vec();
+        let nibbles = input
+            .iter()
+            .flat_map(|byte| [byte >> 4, byte & 0xf])
+            .collect_vec();
+        let expected = Sha256::digest(&input);
+        let prover = mock_fn_circuit(
+            vec![nibbles.clone()],
+            vec![64],
+            synth,
+            bytes_to_instances(&expected),
+        );
+        assert_eq!(prover.verify(), Ok(()));
+
+        // (0, 0x6e) recombines to the same byte as (0x6, 0xe), but 0x6e is not a nibble.
+        let mut wide_nibbles = nibbles;
+        assert_eq!(input[0], 0x6e);
+        wide_nibbles[0] = 0;
+        wide_nibbles[1] = 0x6e;
+        let prover = mock_fn_circuit(
+            vec![wide_nibbles],
+            vec![64],
+            synth,
+            bytes_to_instances(&expected),
+        );
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_configure_with_spread_columns() {
+        let mut meta = ConstraintSystem::<Fr>::default();
+        let range = RangeConfig::configure(
+            &mut meta,
+            Vertical,
+            &[FnCircuit::NUM_ADVICE],
+            &[FnCircuit::NUM_LOOKUP_ADVICE],
+            FnCircuit::NUM_FIXED,
+            FnCircuit::LOOKUP_BITS,
+            0,
+            FnCircuit::K as usize,
+        );
+        let dense_columns = (0..2).map(|_| meta.advice_column()).collect_vec();
+        let spread_columns = (0..2).map(|_| meta.advice_column()).collect_vec();
+        let num_advice_columns = meta.num_advice_columns();
+        let sha256 = Sha256DynamicConfig::configure_with_spread_columns(
+            &mut meta,
+            vec![64],
+            range,
+            8,
+            dense_columns.clone(),
+            spread_columns.clone(),
+            true,
+        );
+        assert_eq!(meta.num_advice_columns(), num_advice_columns);
+        let (denses, spreads) = sha256.spread_advice_columns();
+        assert_eq!(denses, &dense_columns[..]);
+        assert_eq!(spreads, &spread_columns[..]);
+    }
+
+    #[test]
+    fn test_reset() {
+        fn synth<'v>(
+            sha256: &mut Sha256DynamicConfig<Fr>,
+            ctx: &mut Context<'v, Fr>,
+            inputs: &[Vec<u8>],
+        ) -> Result<Vec<AssignedValue<'v, Fr>>, Error> {
+            let (result, stats) = sha256.digest_with_stats(ctx, &inputs[0], None)?;
+            assert_eq!(sha256.spread_config.num_lookups(), stats.spread_lookups);
+            assert_eq!(sha256.cur_hash_idx, 1);
+            sha256.reset();
+            assert_eq!(sha256.spread_config.num_lookups(), 0);
+            assert_eq!(sha256.cur_hash_idx, 0);
+            Ok(result.output_bytes)
+        }
+        let input = b"abc".to_vec();
+        let expected = Sha256::digest(&input);
+        let prover = mock_fn_circuit(vec![input], vec![64], synth, bytes_to_instances(&expected));
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    #[should_panic(expected = "use a larger k")]
+    fn test_spread_table_too_large_for_k() {
+        let mut meta = ConstraintSystem::<Fr>::default();
+        let k = 8;
+        let range = RangeConfig::configure(&mut meta, Vertical, &[1], &[1], 1, 4, 0, k);
+        Sha256DynamicConfig::configure(&mut meta, vec![64], range, 16, 1, true);
+    }
+
+    #[test]
+    fn test_check_spread_table_size() {
+        let mut meta = ConstraintSystem::<Fr>::default();
+        let k = 8;
+        let range = RangeConfig::configure(&mut meta, Vertical, &[1], &[1], 1, 4, 0, k);
+        assert!(Sha256DynamicConfig::check_spread_table_size(&range, 1 << 4).is_ok());
+        match Sha256DynamicConfig::check_spread_table_size(&range, 1 << 16) {
+            Err(Error::NotEnoughRowsAvailable { current_k }) => assert_eq!(current_k, k as u32),
+            _ => panic!("expected NotEnoughRowsAvailable"),
+        }
+    }
+
+    #[test]
+    fn test_digest_evm_words_round_trip() {
+        let digest = sha256_array(b"abc");
+        let (hi, lo) = digest_to_hi_lo::<Fr>(&digest);
+        assert_eq!(hi, Fr::from_u128(0xba7816bf8f01cfea414140de5dae2223));
+        let words = instances_to_evm_words(&[hi, lo]);
+        assert_eq!(&words[0][16..32], &digest[0..16]);
+        assert_eq!(&words[1][16..32], &digest[16..32]);
+        assert_eq!(evm_words_to_digest(&words[0], &words[1]), digest);
+    }
+
+    #[test]
+    fn test_compress_block_no_feed_forward() {
+        fn synth<'v>(
+            sha256: &mut Sha256DynamicConfig<Fr>,
+            ctx: &mut Context<'v, Fr>,
+            inputs: &[Vec<u8>],
+        ) -> Result<Vec<AssignedValue<'v, Fr>>, Error> {
+            let state = load_state(sha256, ctx, &INIT_STATE);
+            let block = load_bytes(ctx, sha256.range(), &inputs[0]);
+            let working = sha256.compress_block_no_feed_forward(ctx, &state, &block)?;
+            let next_state = sha256_feed_forward(ctx, sha256.range(), &working, &state);
+            Ok([working, next_state].concat())
+        }
+        let mut abc_block = vec![0u8; 64];
+        abc_block[0..3].copy_from_slice(b"abc");
+        abc_block[3] = 0x80;
+        abc_block[63] = 24;
+        // FIPS 180-2 "abc": working variables after round 63, then H(1).
+        let working: [u32; NUM_STATE_WORD] = [
+            0x506e3058, 0xd39a2165, 0x04d24d6c, 0xb85e2ce9, 0x5ef50f24, 0xfb121210, 0x948d25b6,
+            0x961f4894,
+        ];
+        let next_state = compress_block(INIT_STATE, block_words(&abc_block));
+        let public = working
+            .iter()
+            .chain(next_state.iter())
+            .map(|word| Fr::from(*word as u64))
+            .collect_vec();
+        let prover = mock_fn_circuit(vec![abc_block], vec![], synth, public);
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    // `inputs[1]` holds the big-endian 2-byte difficulty threshold, exposed as the public input.
+    fn synth_proof_of_work<'v>(
+        sha256: &mut Sha256DynamicConfig<Fr>,
+        ctx: &mut Context<'v, Fr>,
+        inputs: &[Vec<u8>],
+    ) -> Result<Vec<AssignedValue<'v, Fr>>, Error> {
+        let threshold_val = u16::from_be_bytes(inputs[1][..].try_into().unwrap());
+        let threshold = sha256
+            .range()
+            .gate()
+            .load_witness(ctx, Value::known(Fr::from(threshold_val as u64)));
+        let result = sha256.digest(ctx, &inputs[0], None)?;
+        sha256.assert_digest_below(ctx, &result.output_bytes, &threshold, 2);
+        Ok(vec![threshold])
+    }
+
+    #[test]
+    fn test_assert_digest_below() {
+        const THRESHOLD: u16 = 0x0010;
+        let good_nonce = b"pow nonce 2825".to_vec();
+        let bad_nonce = b"pow nonce 0".to_vec();
+        assert!(Sha256::digest(&good_nonce)[0..2] < THRESHOLD.to_be_bytes()[..]);
+        assert!(Sha256::digest(&bad_nonce)[0..2] >= THRESHOLD.to_be_bytes()[..]);
+        let public = vec![Fr::from(THRESHOLD as u64)];
+
+        let inputs = vec![good_nonce, THRESHOLD.to_be_bytes().to_vec()];
+        let prover = mock_fn_circuit(inputs, vec![64], synth_proof_of_work, public.clone());
+        assert_eq!(prover.verify(), Ok(()));
+
+        let inputs = vec![bad_nonce, THRESHOLD.to_be_bytes().to_vec()];
+        let prover = mock_fn_circuit(inputs, vec![64], synth_proof_of_work, public);
+        assert!(prover.verify().is_err());
+    }
+
+    // Writes `inputs[0] = [dense, spread]` directly into the first spread column pair.
+    fn synth_raw_spread_pair<'v>(
+        sha256: &mut Sha256DynamicConfig<Fr>,
+        ctx: &mut Context<'v, Fr>,
+        inputs: &[Vec<u8>],
+    ) -> Result<Vec<AssignedValue<'v, Fr>>, Error> {
+        let (denses, spreads) = sha256.spread_advice_columns();
+        let (dense, spread) = (denses[0], spreads[0]);
+        ctx.region.assign_advice(
+            || "raw dense",
+            dense,
+            0,
+            || Value::known(Fr::from(inputs[0][0] as u64)),
+        )?;
+        ctx.region.assign_advice(
+            || "raw spread",
+            spread,
+            0,
+            || Value::known(Fr::from(inputs[0][1] as u64)),
+        )?;
+        Ok(vec![])
+    }
+
+    #[test]
+    fn test_spread_lookup_rejects_inconsistent_pair() {
+        // spread(0b11) = 0b0101.
+        let prover = mock_fn_circuit(vec![vec![3, 5]], vec![], synth_raw_spread_pair, vec![]);
+        assert_eq!(prover.verify(), Ok(()));
+        let prover = mock_fn_circuit(vec![vec![3, 6]], vec![], synth_raw_spread_pair, vec![]);
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_digest_length_prefixed() {
+        fn synth<'v>(
+            sha256: &mut Sha256DynamicConfig<Fr>,
+            ctx: &mut Context<'v, Fr>,
+            inputs: &[Vec<u8>],
+        ) -> Result<Vec<AssignedValue<'v, Fr>>, Error> {
+            let result = sha256.digest_length_prefixed(ctx, &inputs[0])?;
+            Ok(result.output_bytes)
+        }
+        let msg = "length-prefixed UTF-8 \u{2713}".as_bytes().to_vec();
+        let mut reference = Sha256::new();
+        reference.update((msg.len() as u32).to_be_bytes());
+        reference.update(&msg);
+        let expected = reference.finalize();
+        let prover = mock_fn_circuit(
+            vec![msg.clone()],
+            vec![64],
+            synth,
+            bytes_to_instances(&expected),
+        );
+        assert_eq!(prover.verify(), Ok(()));
+
+        // a prefix disagreeing with the message length, or no prefix at all, is rejected.
+        let mut reference = Sha256::new();
+        reference.update((msg.len() as u32 + 1).to_be_bytes());
+        reference.update(&msg);
+        for wrong in [reference.finalize(), Sha256::digest(&msg)] {
+            let prover = mock_fn_circuit(
+                vec![msg.clone()],
+                vec![64],
+                synth,
+                bytes_to_instances(&wrong),
+            );
+            assert!(prover.verify().is_err());
+        }
+    }
+
+    #[cfg(feature = "kzg")]
+    #[test]
+    fn test_prove_and_verify_digest_kzg() {
+        use halo2_base::halo2_proofs::halo2curves::bn256::Bn256;
+        use halo2_base::halo2_proofs::{
+            plonk::{keygen_pk, keygen_vk},
+            poly::kzg::commitment::ParamsKZG,
+        };
+        use prover::kzg;
+        use rand::rngs::OsRng;
+
+        let input = b"abc".to_vec();
+        let expected = Sha256::digest(&input);
+        let circuit = FnCircuit {
+            inputs: vec![input],
+            max_byte_sizes: vec![64],
+            synth: synth_digest_all,
+        };
+        let params = ParamsKZG::<Bn256>::setup(FnCircuit::K, OsRng);
+        let vk = keygen_vk(&params, &circuit).unwrap();
+        let pk = keygen_pk(&params, vk, &circuit).unwrap();
+        let proof =
+            kzg::gen_proof(&params, &pk, circuit, &prover::digest_instances(&expected)).unwrap();
+        assert!(kzg::verify_digest_proof(&params, pk.get_vk(), &proof, &expected).is_ok());
+        let wrong = Sha256::digest(b"abd");
+        assert!(kzg::verify_digest_proof(&params, pk.get_vk(), &proof, &wrong).is_err());
+    }
+
+    #[cfg(feature = "ipa")]
+    #[test]
+    fn test_prove_and_verify_digest_ipa() {
+        use halo2_base::halo2_proofs::halo2curves::pasta::{EqAffine, Fp};
+        use halo2_base::halo2_proofs::{
+            plonk::{keygen_pk, keygen_vk},
+            poly::{commitment::ParamsProver, ipa::commitment::ParamsIPA},
+        };
+        use prover::ipa;
+
+        // the generic test circuit over the Pasta scalar field, exposing both digests.
+        let test_inputs = vec![b"abc".to_vec(), vec![]];
+        let expected = test_inputs
+            .iter()
+            .flat_map(|input| Sha256::digest(input).to_vec())
+            .collect_vec();
+        let circuit = TestCircuit::<Fp> {
+            test_inputs,
+            precomputed_input_lens: vec![0, 0],
+            _f: PhantomData,
+        };
+        let params = ParamsIPA::<EqAffine>::new(17);
+        let vk = keygen_vk(&params, &circuit).unwrap();
+        let pk = keygen_pk(&params, vk, &circuit).unwrap();
+        let proof =
+            ipa::gen_proof(&params, &pk, circuit, &prover::digest_instances(&expected)).unwrap();
+        assert!(ipa::verify_digest_proof(&params, pk.get_vk(), &proof, &expected).is_ok());
+        let mut wrong = expected;
+        wrong[0] ^= 1;
+        assert!(ipa::verify_digest_proof(&params, pk.get_vk(), &proof, &wrong).is_err());
+    }
+
+    fn hmac_sha256_reference(key: &[u8], msg: &[u8]) -> Vec<u8> {
+        let mut padded_key = key.to_vec();
+        padded_key.resize(64, 0);
+        let inner_key = padded_key.iter().map(|byte| byte ^ 0x36).collect_vec();
+        let outer_key = padded_key.iter().map(|byte| byte ^ 0x5c).collect_vec();
+        let inner = Sha256::digest(&[inner_key, msg.to_vec()].concat());
+        Sha256::digest(&[outer_key, inner.to_vec()].concat()).to_vec()
+    }
+
+    #[test]
+    fn test_hmac_sha256() {
+        fn synth<'v>(
+            sha256: &mut Sha256DynamicConfig<Fr>,
+            ctx: &mut Context<'v, Fr>,
+            inputs: &[Vec<u8>],
+        ) -> Result<Vec<AssignedValue<'v, Fr>>, Error> {
+            let range = sha256.range().clone();
+            let key = load_bytes(ctx, &range, &inputs[0]);
+            let msg = load_bytes(ctx, &range, &inputs[1]);
+            hmac(sha256, ctx, range.gate(), &key, &msg)
+        }
+        // RFC 4231 test case 2.
+        let key = b"Jefe".to_vec();
+        let msg = b"what do ya want for nothing?".to_vec();
+        let expected = hmac_sha256_reference(&key, &msg);
+        assert_eq!(
+            hex::encode(&expected),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        let prover = mock_fn_circuit(
+            vec![key, msg],
+            vec![128, 128],
+            synth,
+            bytes_to_instances(&expected),
+        );
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_output_words_abc() {
+        fn synth<'v>(
+            sha256: &mut Sha256DynamicConfig<Fr>,
+            ctx: &mut Context<'v, Fr>,
+            inputs: &[Vec<u8>],
+        ) -> Result<Vec<AssignedValue<'v, Fr>>, Error> {
+            Ok(sha256.digest(ctx, &inputs[0], None)?.output_words)
+        }
+        let words: [u32; NUM_STATE_WORD] = [
+            0xba7816bf, 0x8f01cfea, 0x414140de, 0x5dae2223, 0xb00361a3, 0x96177a9c, 0xb410ff61,
+            0xf20015ad,
+        ];
+        let public = words.iter().map(|word| Fr::from(*word as u64)).collect();
+        let prover = mock_fn_circuit(vec![b"abc".to_vec()], vec![64], synth, public);
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_digest_to_secp256k1_scalar() {
+        use halo2_base::halo2_proofs::halo2curves::secp256k1::Fq;
+        use halo2_ecc::fields::fp::FpConfig;
+        use num_bigint::BigUint;
+        fn synth<'v>(
+            sha256: &mut Sha256DynamicConfig<Fr>,
+            ctx: &mut Context<'v, Fr>,
+            inputs: &[Vec<u8>],
+        ) -> Result<Vec<AssignedValue<'v, Fr>>, Error> {
+            let fp_chip =
+                FpConfig::<Fr, Fq>::construct(sha256.range().clone(), 88, 3, modulus::<Fq>());
+            let result = sha256.digest(ctx, &inputs[0], None)?;
+            let scalar = digest_to_field_element(ctx, &fp_chip, &result.output_bytes);
+            Ok([scalar.truncation.limbs, vec![scalar.native]].concat())
+        }
+        // a message whose digest exceeds the secp256k1 group order is astronomically unlikely,
+        // so this checks the limb layout; the reduction itself is halo2-ecc's `carry_mod`.
+        let input = b"ecdsa message".to_vec();
+        let digest = BigUint::from_bytes_be(&Sha256::digest(&input));
+        let scalar = &digest % modulus::<Fq>();
+        let limb_mask = (BigUint::from(1u32) << 88) - 1u32;
+        let mut public = (0..3)
+            .map(|idx| biguint_to_fe::<Fr>(&((&scalar >> (88 * idx)) & &limb_mask)))
+            .collect_vec();
+        public.push(biguint_to_fe(&(&scalar % modulus::<Fr>())));
+        let prover = mock_fn_circuit(vec![input], vec![64], synth, public);
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_digest_with_midstate_hmac_inner() {
+        const KEY: &[u8] = b"Jefe";
+        fn ipad_block() -> Vec<u8> {
+            let mut block = KEY.to_vec();
+            block.resize(64, 0);
+            block.iter().map(|byte| byte ^ 0x36).collect()
+        }
+        fn synth<'v>(
+            sha256: &mut Sha256DynamicConfig<Fr>,
+            ctx: &mut Context<'v, Fr>,
+            inputs: &[Vec<u8>],
+        ) -> Result<Vec<AssignedValue<'v, Fr>>, Error> {
+            let state = load_state(sha256, ctx, &midstate(&ipad_block()));
+            let result = sha256.digest_with_iv(ctx, &inputs[0], &state, 64)?;
+            Ok(result.output_bytes)
+        }
+        let msg = b"what do ya want for nothing?".to_vec();
+        let expected = Sha256::digest(&[ipad_block(), msg.clone()].concat());
+        let prover = mock_fn_circuit(vec![msg], vec![64], synth, bytes_to_instances(&expected));
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_digest_equals() {
+        fn synth<'v>(
+            sha256: &mut Sha256DynamicConfig<Fr>,
+            ctx: &mut Context<'v, Fr>,
+            inputs: &[Vec<u8>],
+        ) -> Result<Vec<AssignedValue<'v, Fr>>, Error> {
+            let digests = synth_digest_all(sha256, ctx, inputs)?;
+            let (first, rest) = digests.split_at(32);
+            let (second, third) = rest.split_at(32);
+            let same = sha256.digest_equals(ctx, first, second);
+            let different = sha256.digest_equals(ctx, first, third);
+            Ok(vec![same, different])
+        }
+        let inputs = vec![b"abc".to_vec(), b"abc".to_vec(), b"abd".to_vec()];
+        let public = vec![Fr::from(1u64), Fr::from(0u64)];
+        let prover = mock_fn_circuit(inputs, vec![64, 64, 64], synth, public);
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_digest_to_keccak_lanes() {
+        fn synth<'v>(
+            sha256: &mut Sha256DynamicConfig<Fr>,
+            ctx: &mut Context<'v, Fr>,
+            inputs: &[Vec<u8>],
+        ) -> Result<Vec<AssignedValue<'v, Fr>>, Error> {
+            let result = sha256.digest(ctx, &inputs[0], None)?;
+            Ok(sha256.digest_to_keccak_lanes(ctx, &result.output_bytes))
+        }
+        let digest = Sha256::digest(b"abc");
+        // ba7816bf 8f01cfea ... read little-endian per 8-byte lane.
+        assert_eq!(
+            u64::from_le_bytes(digest[0..8].try_into().unwrap()),
+            0xeacf018fbf1678ba
+        );
+        let public = digest
+            .chunks(8)
+            .map(|lane| Fr::from(u64::from_le_bytes(lane.try_into().unwrap())))
+            .collect();
+        let prover = mock_fn_circuit(vec![b"abc".to_vec()], vec![64], synth, public);
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[cfg(feature = "display")]
+    #[test]
+    fn test_spread_decomposition_display() {
+        let mut meta = ConstraintSystem::<Fr>::default();
+        let config = FnCircuit::configure(&mut meta);
+        let decomposition = config.sha256.spread_decomposition(0x8000_0003);
+        assert_eq!(
+            decomposition.to_string(),
+            "word 0x80000003 = 4 limbs of 8 bits\n\
+             limb 0: dense 0x3 -> spread 0x5\n\
+             limb 1: dense 0x0 -> spread 0x0\n\
+             limb 2: dense 0x0 -> spread 0x0\n\
+             limb 3: dense 0x80 -> spread 0x4000\n\
+             spread 0x4000000000000005"
+        );
+    }
+
+    #[test]
+    fn test_digest_prefix_mac() {
+        fn synth<'v>(
+            sha256: &mut Sha256DynamicConfig<Fr>,
+            ctx: &mut Context<'v, Fr>,
+            inputs: &[Vec<u8>],
+        ) -> Result<Vec<AssignedValue<'v, Fr>>, Error> {
+            let range = sha256.range().clone();
+            let key = load_bytes(ctx, &range, &inputs[0]);
+            let msg = load_bytes(ctx, &range, &inputs[1]);
+            Ok(sha256.digest_prefix_mac(ctx, &key, &msg)?.output_bytes)
+        }
+        let key = vec![0x0b; 20];
+        let msg = b"legacy prefix MAC message spanning more than one block of input".to_vec();
+        let expected = Sha256::digest(&[key.clone(), msg.clone()].concat());
+        let prover = mock_fn_circuit(
+            vec![key, msg],
+            vec![128],
+            synth,
+            bytes_to_instances(&expected),
+        );
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    // Runs `synth` on `inputs` with spread limbs of `LIMB_BITS` bits assigned to
+    // `NUM_SPREAD_COLUMNS` column pairs, or with two 4-bit limbs per lookup when `COMPOSITE`,
+    // optionally trusting the input bytes. Records the cells and lookups `synth` used.
+    struct LimbBitsCircuit<
+        const LIMB_BITS: usize,
+        const NUM_SPREAD_COLUMNS: usize,
+        const COMPOSITE: bool,
+    > {
+        inputs: Vec<Vec<u8>>,
+        synth: SynthFn,
+        trust_input_bytes: bool,
+        stats: std::cell::Cell<Option<DigestStats>>,
+    }
+
+    impl<const LIMB_BITS: usize, const NUM_SPREAD_COLUMNS: usize, const COMPOSITE: bool> Circuit<Fr>
+        for LimbBitsCircuit<LIMB_BITS, NUM_SPREAD_COLUMNS, COMPOSITE>
+    {
+        type Config = TestConfig<Fr>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            unimplemented!()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            let range_config = RangeConfig::configure(
+                meta,
+                Vertical,
+                &[FnCircuit::NUM_ADVICE],
+                &[FnCircuit::NUM_LOOKUP_ADVICE],
+                FnCircuit::NUM_FIXED,
+                FnCircuit::LOOKUP_BITS,
+                0,
+                FnCircuit::K as usize,
+            );
+            let hash_column = meta.instance_column();
+            meta.enable_equality(hash_column);
+            let sha256 = if COMPOSITE {
+                Sha256DynamicConfig::configure_with_composite_spread(
+                    meta,
+                    vec![128],
+                    range_config,
+                    NUM_SPREAD_COLUMNS,
+                    true,
+                )
+            } else {
+                Sha256DynamicConfig::configure(
+                    meta,
+                    vec![128],
+                    range_config,
+                    LIMB_BITS,
+                    NUM_SPREAD_COLUMNS,
+                    true,
+                )
+            };
+            TestConfig {
+                sha256,
+                hash_column,
+            }
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            let mut sha256 = config.sha256.clone();
+            sha256.trust_input_bytes(self.trust_input_bytes);
+            let range = sha256.range().clone();
+            range.load_lookup_table(&mut layouter)?;
+            sha256.load(&mut layouter)?;
+            let mut first_pass = SKIP_FIRST_PASS;
+            let mut output_cells = vec![];
+            layouter.assign_region(
+                || "sha256 limb bits",
+                |region| {
+                    if first_pass {
+                        first_pass = false;
+                        return Ok(());
+                    }
+                    let ctx = &mut sha256.new_context(region);
+                    let advice_cells = ctx.total_advice;
+                    let range_lookup_cells = ctx.cells_to_lookup.len();
+                    let spread_lookups = sha256.spread_config.num_lookups();
+                    let outputs = (self.synth)(&mut sha256, ctx, &self.inputs)?;
+                    self.stats.set(Some(DigestStats {
+                        advice_cells: ctx.total_advice - advice_cells,
+                        range_lookup_cells: ctx.cells_to_lookup.len() - range_lookup_cells,
+                        spread_lookups: sha256.spread_config.num_lookups() - spread_lookups,
+                    }));
+                    output_cells = outputs.iter().map(|v| v.cell()).collect();
+                    range.finalize(ctx);
+                    Ok(())
+                },
+            )?;
+            constrain_cells_to_instance(&mut layouter, &output_cells, config.hash_column, 0)
+        }
+    }
+
+    // Checks the digest of `input` and returns the number of spread lookups.
+    fn spread_circuit_lookups<const LIMB_BITS: usize, const NUM_SPREAD_COLUMNS: usize>(
+        input: &[u8],
+    ) -> usize {
+        limb_bits_digest_stats::<LIMB_BITS, NUM_SPREAD_COLUMNS, false>(input, false).spread_lookups
+    }
+
+    // Checks the digest of `input` and returns the stats of the `digest` call.
+    fn limb_bits_digest_stats<
+        const LIMB_BITS: usize,
+        const NUM_SPREAD_COLUMNS: usize,
+        const COMPOSITE: bool,
+    >(
+        input: &[u8],
+        trust_input_bytes: bool,
+    ) -> DigestStats {
+        let circuit = LimbBitsCircuit::<LIMB_BITS, NUM_SPREAD_COLUMNS, COMPOSITE> {
+            inputs: vec![input.to_vec()],
+            synth: synth_digest_all,
+            trust_input_bytes,
+            stats: std::cell::Cell::new(None),
+        };
+        let public = bytes_to_instances(&Sha256::digest(input));
+        let prover = MockProver::run(FnCircuit::K, &circuit, vec![public]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+        circuit.stats.get().unwrap()
+    }
+
+    // Spreads the big-endian 16-bit words of `inputs[0]`.
+    fn synth_spread_words<'v>(
+        sha256: &mut Sha256DynamicConfig<Fr>,
+        ctx: &mut Context<'v, Fr>,
+        inputs: &[Vec<u8>],
+    ) -> Result<Vec<AssignedValue<'v, Fr>>, Error> {
+        let range = sha256.range().clone();
+        let mut spreads = vec![];
+        for word in inputs[0].chunks(2) {
+            let word = u16::from_be_bytes([word[0], word[1]]);
+            let assigned = range
+                .gate()
+                .load_witness(ctx, Value::known(Fr::from(word as u64)));
+            spreads.push(sha256.spread_config.spread(ctx, &range, &assigned)?);
+        }
+        Ok(spreads)
+    }
+
+    // Runs `synth_spread_words` on `words`, exposing their spreads.
+    fn mock_spread_words<const COMPOSITE: bool>(words: &[u16], public: Vec<Fr>) -> MockProver<Fr> {
+        let circuit = LimbBitsCircuit::<4, { FnCircuit::NUM_SPREAD_COLUMNS }, COMPOSITE> {
+            inputs: vec![words.iter().flat_map(|word| word.to_be_bytes()).collect()],
+            synth: synth_spread_words,
+            trust_input_bytes: false,
+            stats: std::cell::Cell::new(None),
+        };
+        MockProver::run(FnCircuit::K, &circuit, vec![public]).unwrap()
+    }
+
+    #[test]
+    fn test_composite_spread_words() {
+        let mut rng = thread_rng();
+        let mut words = (0..64).map(|_| rng.gen::<u16>()).collect_vec();
+        words.extend_from_slice(&[0, 0x000f, 0x00f0, 0xffff]);
+        let spreads = words
+            .iter()
+            .map(|word| Fr::from(utils::spread_u32(*word as u32)))
+            .collect_vec();
+        // the composite and the plain 4-bit configs spread every word the same way.
+        assert_eq!(
+            mock_spread_words::<true>(&words, spreads.clone()).verify(),
+            Ok(())
+        );
+        assert_eq!(
+            mock_spread_words::<false>(&words, spreads.clone()).verify(),
+            Ok(())
+        );
+
+        let mut wrong = spreads;
+        wrong[3] += Fr::one();
+        assert!(mock_spread_words::<true>(&words, wrong).verify().is_err());
+    }
+
+    #[test]
+    fn test_composite_spread_digest() {
+        let mut rng = thread_rng();
+        for len in [0, 55, 56, 64, 100, 119] {
+            let input = (0..len).map(|_| rng.gen::<u8>()).collect_vec();
+            let lookups_4 = limb_bits_digest_stats::<4, { FnCircuit::NUM_SPREAD_COLUMNS }, false>(
+                &input, false,
+            )
+            .spread_lookups;
+            let lookups_composite =
+                limb_bits_digest_stats::<4, { FnCircuit::NUM_SPREAD_COLUMNS }, true>(&input, false)
+                    .spread_lookups;
+            // every lookup checks a pair of 4-bit limbs.
+            assert_eq!(lookups_4, 2 * lookups_composite);
+            assert_eq!(lookups_composite, spread_circuit_lookups::<8, 4>(&input));
+        }
+    }
+
+    #[test]
+    fn test_config_params_hash() {
+        use std::collections::HashMap;
+        let params = Sha256ConfigParams {
+            k: FnCircuit::K,
+            max_variable_byte_sizes: vec![64, 128],
+            num_bits_lookup: 8,
+            num_spread_columns: FnCircuit::NUM_SPREAD_COLUMNS,
+            is_input_range_check: true,
+        };
+        let mut wider = params.clone();
+        wider.num_spread_columns += 1;
+        let mut larger = params.clone();
+        larger.k += 1;
+        assert_ne!(params, wider);
+        assert_ne!(params, larger);
+
+        let mut vk_cache = HashMap::new();
+        vk_cache.insert(params.clone(), "vk");
+        vk_cache.insert(wider, "wider vk");
+        vk_cache.insert(larger, "larger vk");
+        assert_eq!(vk_cache.len(), 3);
+        assert_eq!(vk_cache.get(&params), Some(&"vk"));
+        vk_cache.insert(params.clone(), "same vk");
+        assert_eq!(vk_cache.len(), 3);
+
+        let mut meta = ConstraintSystem::<Fr>::default();
+        let range = RangeConfig::configure(
+            &mut meta,
+            Vertical,
+            &[FnCircuit::NUM_ADVICE],
+            &[FnCircuit::NUM_LOOKUP_ADVICE],
+            FnCircuit::NUM_FIXED,
+            FnCircuit::LOOKUP_BITS,
+            0,
+            FnCircuit::K as usize,
+        );
+        let sha256 = Sha256DynamicConfig::configure_with_params(&mut meta, range, &params);
+        assert_eq!(sha256.max_variable_byte_sizes, vec![64, 128]);
+    }
+
+    #[test]
+    #[should_panic(expected = "the range config is for k = 18 but the params are for k = 19")]
+    fn test_config_params_k_mismatch() {
+        let params = Sha256ConfigParams {
+            k: FnCircuit::K + 1,
+            max_variable_byte_sizes: vec![64],
+            num_bits_lookup: 8,
+            num_spread_columns: FnCircuit::NUM_SPREAD_COLUMNS,
+            is_input_range_check: true,
+        };
+        let mut meta = ConstraintSystem::<Fr>::default();
+        let range = RangeConfig::configure(
+            &mut meta,
+            Vertical,
+            &[FnCircuit::NUM_ADVICE],
+            &[FnCircuit::NUM_LOOKUP_ADVICE],
+            FnCircuit::NUM_FIXED,
+            FnCircuit::LOOKUP_BITS,
+            0,
+            FnCircuit::K as usize,
+        );
+        Sha256DynamicConfig::configure_with_params(&mut meta, range, &params);
+    }
+
+    // `inputs = [prefix, field, [present]]`.
+    fn synth_digest_optional<'v>(
+        sha256: &mut Sha256DynamicConfig<Fr>,
+        ctx: &mut Context<'v, Fr>,
+        inputs: &[Vec<u8>],
+    ) -> Result<Vec<AssignedValue<'v, Fr>>, Error> {
+        let range = sha256.range().clone();
+        let prefix = load_bytes(ctx, &range, &inputs[0]);
+        let field = load_bytes(ctx, &range, &inputs[1]);
+        let present = load_bytes(ctx, &range, &inputs[2]).pop().unwrap();
+        Ok(sha256
+            .digest_optional(ctx, &prefix, &field, &present)?
+            .output_bytes)
+    }
+
+    #[test]
+    fn test_digest_optional() {
+        let prefix = b"name=alice;".to_vec();
+        let field = b"email=alice@example.com;".to_vec();
+        for present in [true, false] {
+            let message = if present {
+                [prefix.clone(), field.clone()].concat()
+            } else {
+                prefix.clone()
+            };
+            let expected = Sha256::digest(&message);
+            let inputs = vec![prefix.clone(), field.clone(), vec![present as u8]];
+            let prover = mock_fn_circuit(
+                inputs,
+                vec![64],
+                synth_digest_optional,
+                bytes_to_instances(&expected),
+            );
+            assert_eq!(prover.verify(), Ok(()));
+        }
+
+        // claiming the field is present while proving the digest of the prefix alone.
+        let prover = mock_fn_circuit(
+            vec![prefix.clone(), field.clone(), vec![1]],
+            vec![64],
+            synth_digest_optional,
+            bytes_to_instances(&Sha256::digest(&prefix)),
+        );
+        assert!(prover.verify().is_err());
+
+        // a non-boolean `present` hashes the prefix alone but fails the flag's range check.
+        let prover = mock_fn_circuit(
+            vec![prefix.clone(), field, vec![2]],
+            vec![64],
+            synth_digest_optional,
+            bytes_to_instances(&Sha256::digest(&prefix)),
+        );
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_verify_merkle_leaf() {
+        fn synth<'v>(
+            sha256: &mut Sha256DynamicConfig<Fr>,
+            ctx: &mut Context<'v, Fr>,
+            inputs: &[Vec<u8>],
+        ) -> Result<Vec<AssignedValue<'v, Fr>>, Error> {
+            let range = sha256.range().clone();
+            let gate = range.gate();
+            let path = vec![
+                load_bytes(ctx, &range, &inputs[1]),
+                load_bytes(ctx, &range, &inputs[2]),
+            ];
+            let root = load_bytes(ctx, &range, &inputs[3]);
+            let index_bits = (0..path.len())
+                .map(|idx| {
+                    let bit = (MERKLE_LEAF_INDEX >> idx) & 1;
+                    gate.load_witness(ctx, Value::known(Fr::from(bit as u64)))
+                })
+                .collect_vec();
+            sha256.verify_merkle_leaf(ctx, &inputs[0], &path, &index_bits, &root)?;
+            Ok(root)
+        }
+        let inputs = merkle_test_inputs();
+        let public = bytes_to_instances(&inputs[3]);
+        let prover = mock_fn_circuit(inputs.clone(), vec![64], synth, public.clone());
+        assert_eq!(prover.verify(), Ok(()));
+
+        let mut wrong_data = inputs;
+        wrong_data[0] = b"d".to_vec();
+        let prover = mock_fn_circuit(wrong_data, vec![64], synth, public);
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_pad_message() {
+        for len in 0..300 {
+            let msg = (0..len).map(|idx| idx as u8).collect_vec();
+            let mut expected = msg.clone();
+            expected.push(0x80);
+            while expected.len() % 64 != 56 {
+                expected.push(0);
+            }
+            expected.extend_from_slice(&(8 * len as u64).to_be_bytes());
+            let padded = pad_message(&msg);
+            assert_eq!(padded, expected, "len {}", len);
+            assert_eq!(padded.len(), 64 * num_blocks_for_len(len));
+
+            let mut state = INIT_STATE;
+            let blocks = padded
+                .chunks(64)
+                .map(|block| GenericArray::clone_from_slice(block))
+                .collect_vec();
+            compress256(&mut state, &blocks);
+            let digest = state
+                .iter()
+                .flat_map(|word| word.to_be_bytes())
+                .collect_vec();
+            assert_eq!(digest, Sha256::digest(&msg).to_vec());
+        }
+    }
+
+    #[test]
+    fn test_digest_words32() {
+        fn synth<'v>(
+            sha256: &mut Sha256DynamicConfig<Fr>,
+            ctx: &mut Context<'v, Fr>,
+            inputs: &[Vec<u8>],
+        ) -> Result<Vec<AssignedValue<'v, Fr>>, Error> {
+            let gate = sha256.range().gate().clone();
+            let words = inputs[0]
+                .chunks(32)
+                .map(|bytes| {
+                    let word = biguint_to_fe(&num_bigint::BigUint::from_bytes_be(bytes));
+                    gate.load_witness(ctx, Value::known(word))
+                })
+                .collect_vec();
+            Ok(sha256.digest_words32(ctx, &words)?.output_bytes)
+        }
+        fn synth_less_than_modulus<'v>(
+            sha256: &mut Sha256DynamicConfig<Fr>,
+            ctx: &mut Context<'v, Fr>,
+            inputs: &[Vec<u8>],
+        ) -> Result<Vec<AssignedValue<'v, Fr>>, Error> {
+            let range = sha256.range().clone();
+            let bytes = load_bytes(ctx, &range, &inputs[0]);
+            assert_be_bytes_less_than(ctx, &range, &bytes, &modulus::<Fr>().to_bytes_be());
+            Ok(vec![])
+        }
+        let words = [Fr::from(1u64), Fr::from(u64::MAX), -Fr::from(1u64)];
+        let byte_stream = words
+            .iter()
+            .flat_map(|word| {
+                let bytes = fe_to_biguint(word).to_bytes_be();
+                [vec![0u8; 32 - bytes.len()], bytes].concat()
+            })
+            .collect_vec();
+        let expected = Sha256::digest(&byte_stream);
+        let prover = mock_fn_circuit(
+            vec![byte_stream.clone()],
+            vec![128],
+            synth,
+            bytes_to_instances(&expected),
+        );
+        assert_eq!(prover.verify(), Ok(()));
+
+        // `1 + p` encodes the same field element as 1 but is not below the modulus.
+        let non_canonical = (fe_to_biguint(&words[0]) + modulus::<Fr>()).to_bytes_be();
+        assert_eq!(non_canonical.len(), 32);
+        let prover = mock_fn_circuit(
+            vec![non_canonical.clone()],
+            vec![],
+            synth_less_than_modulus,
+            vec![],
+        );
+        assert!(prover.verify().is_err());
+        let forged = Sha256::digest(&[non_canonical, byte_stream[32..].to_vec()].concat());
+        let prover = mock_fn_circuit(
+            vec![byte_stream],
+            vec![128],
+            synth,
+            bytes_to_instances(&forged),
+        );
+        assert!(prover.verify().is_err());
+    }
+
+    // Only loads the lookup tables, with the spread table loaded twice: through the same
+    // config, or through a clone taken after (or, when `clone_before_load`, before) the first load.
+    #[derive(Clone)]
+    struct DoubleLoadCircuit {
+        through_clone: bool,
+        clone_before_load: bool,
+    }
+
+    impl Circuit<Fr> for DoubleLoadCircuit {
+        type Config = TestConfig<Fr>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            self.clone()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            FnCircuit::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            let sha256 = config.sha256;
+            sha256.range().load_lookup_table(&mut layouter)?;
+            let early_clone = sha256.clone();
+            sha256.load(&mut layouter)?;
+            if !self.through_clone {
+                sha256.load(&mut layouter)
+            } else if self.clone_before_load {
+                early_clone.load(&mut layouter)
+            } else {
+                sha256.clone().load(&mut layouter)
+            }
+        }
+    }
+
+    // Hashes `input` with the config saved by an earlier synthesis, if any, after resetting it,
+    // as a prover reusing the config of keygen would.
+    #[derive(Clone)]
+    struct ReusedConfigCircuit {
+        input: Vec<u8>,
+        saved: std::rc::Rc<std::cell::RefCell<Option<Sha256DynamicConfig<Fr>>>>,
+    }
+
+    impl Circuit<Fr> for ReusedConfigCircuit {
+        type Config = TestConfig<Fr>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            self.clone()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            FnCircuit::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            let saved = self.saved.borrow().clone();
+            let mut sha256 = match saved {
+                Some(mut sha256) => {
+                    sha256.reset();
+                    sha256
+                }
+                None => config.sha256.clone(),
+            };
+            sha256.max_variable_byte_sizes = vec![64];
+            let range = sha256.range().clone();
+            range.load_lookup_table(&mut layouter)?;
+            sha256.load(&mut layouter)?;
+            let mut first_pass = SKIP_FIRST_PASS;
+            let mut public_cells = vec![];
+            layouter.assign_region(
+                || "sha256 reused config",
+                |region| {
+                    if first_pass {
+                        first_pass = false;
+                        return Ok(());
+                    }
+                    let ctx = &mut sha256.new_context(region);
+                    let result = sha256.digest(ctx, &self.input, None)?;
+                    public_cells = result.output_bytes.iter().map(|v| v.cell()).collect();
+                    range.finalize(ctx);
+                    Ok(())
+                },
+            )?;
+            *self.saved.borrow_mut() = Some(sha256.clone());
+            constrain_cells_to_instance(&mut layouter, &public_cells, config.hash_column, 0)
+        }
+    }
+
+    // Hashes `input` after loading a spread table shared with other circuits.
+    #[derive(Clone)]
+    struct SharedTableCircuit {
+        table: SpreadTable<Fr>,
+        input: Vec<u8>,
+    }
+
+    impl Circuit<Fr> for SharedTableCircuit {
+        type Config = TestConfig<Fr>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            self.clone()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            FnCircuit::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            let mut sha256 = config.sha256.clone();
+            sha256.max_variable_byte_sizes = vec![64];
+            let range = sha256.range().clone();
+            range.load_lookup_table(&mut layouter)?;
+            sha256.load_with_spread_table(&mut layouter, &self.table)?;
+            let mut first_pass = SKIP_FIRST_PASS;
+            let mut public_cells = vec![];
+            layouter.assign_region(
+                || "sha256 shared table",
+                |region| {
+                    if first_pass {
+                        first_pass = false;
+                        return Ok(());
+                    }
+                    let ctx = &mut sha256.new_context(region);
+                    let result = sha256.digest(ctx, &self.input, None)?;
+                    public_cells = result.output_bytes.iter().map(|v| v.cell()).collect();
+                    range.finalize(ctx);
+                    Ok(())
+                },
+            )?;
+            constrain_cells_to_instance(&mut layouter, &public_cells, config.hash_column, 0)
+        }
+    }
+
+    #[test]
+    fn test_shared_spread_table() {
+        let table = SpreadTable::<Fr>::new(8);
+        assert_eq!(table.rows().len(), 1 << 8);
+        for input in [b"first".to_vec(), b"second".to_vec()] {
+            let circuit = SharedTableCircuit {
+                table: table.clone(),
+                input: input.clone(),
+            };
+            let public = bytes_to_instances(&Sha256::digest(&input));
+            let prover = MockProver::run(FnCircuit::K, &circuit, vec![public]).unwrap();
+            assert_eq!(prover.verify(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn test_load_spread_table_twice() {
+        for &through_clone in [false, true].iter() {
+            let circuit = DoubleLoadCircuit {
+                through_clone,
+                clone_before_load: false,
+            };
+            let prover = MockProver::run(FnCircuit::K, &circuit, vec![vec![]]).unwrap();
+            assert_eq!(prover.verify(), Ok(()));
+        }
+
+        // a clone taken before loading does not know the table is assigned.
+        let circuit = DoubleLoadCircuit {
+            through_clone: true,
+            clone_before_load: true,
+        };
+        assert!(MockProver::run(FnCircuit::K, &circuit, vec![vec![]]).is_err());
+    }
+
+    #[test]
+    fn test_reuse_config_across_syntheses() {
+        let input = b"abc".to_vec();
+        let public = bytes_to_instances(&Sha256::digest(&input));
+        let circuit = ReusedConfigCircuit {
+            input,
+            saved: Default::default(),
+        };
+        // the second synthesis starts from the config the first one loaded the table with.
+        for _ in 0..2 {
+            let prover = MockProver::run(FnCircuit::K, &circuit, vec![public.clone()]).unwrap();
+            assert_eq!(prover.verify(), Ok(()));
+            let saved = circuit.saved.borrow();
+            assert!(saved.as_ref().unwrap().is_spread_table_loaded.get());
+        }
+
+        let mut sha256 = circuit.saved.borrow().clone().unwrap();
+        sha256.reset();
+        assert!(!sha256.is_spread_table_loaded.get());
+    }
+
+    #[test]
+    fn test_block_from_bytes() {
+        fn synth<'v>(
+            sha256: &mut Sha256DynamicConfig<Fr>,
+            ctx: &mut Context<'v, Fr>,
+            inputs: &[Vec<u8>],
+        ) -> Result<Vec<AssignedValue<'v, Fr>>, Error> {
+            let range = sha256.range().clone();
+            let bytes = load_bytes(ctx, &range, &inputs[0]);
+            assert!(Block::try_from(&bytes[1..]).is_err());
+            let block = Block::try_from(&bytes[..])?;
+            let words = block.words(ctx, range.gate());
+            let digest = sha256.digest_block(ctx, &block)?;
+            Ok([words, digest].concat())
+        }
+        let input = (0..64).map(|idx| (7 * idx) as u8).collect_vec();
+        let words = block_words(&input)
+            .iter()
+            .map(|word| Fr::from(*word as u64))
+            .collect_vec();
+        let digest = bytes_to_instances(&Sha256::digest(&input));
+        let public = [words, digest].concat();
+        let prover = mock_fn_circuit(vec![input], vec![], synth, public);
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    // Reference SHA-256 of a big-endian bit string with bit-level padding.
+    fn sha256_bits_reference(bits: &[bool]) -> Vec<u8> {
+        let mut padded = bits.to_vec();
+        padded.push(true);
+        while padded.len() % 512 != 448 {
+            padded.push(false);
+        }
+        padded.extend((0..64).rev().map(|idx| (bits.len() as u64 >> idx) & 1 == 1));
+        let bytes = padded
+            .chunks(8)
+            .map(|byte_bits| {
+                byte_bits
+                    .iter()
+                    .fold(0u8, |byte, bit| (byte << 1) | *bit as u8)
+            })
+            .collect_vec();
+        let state = bytes.chunks(64).fold(INIT_STATE, |state, block| {
+            compress_block(state, block_words(block))
+        });
+        state.iter().flat_map(|word| word.to_be_bytes()).collect()
+    }
+
+    fn synth_digest_bits<'v>(
+        sha256: &mut Sha256DynamicConfig<Fr>,
+        ctx: &mut Context<'v, Fr>,
+        inputs: &[Vec<u8>],
+    ) -> Result<Vec<AssignedValue<'v, Fr>>, Error> {
+        let gate = sha256.range().gate().clone();
+        let bits = inputs[0]
+            .iter()
+            .map(|bit| gate.load_witness(ctx, Value::known(Fr::from(*bit as u64))))
+            .collect_vec();
+        sha256.digest_bits(ctx, &bits, inputs[0].len())
+    }
+
+    #[test]
+    fn test_digest_bits() {
+        // 523 bits: not byte aligned, and the length field spills into a second block.
+        let bits = (0..523)
+            .map(|idx| (idx * 5 + idx / 7) % 3 == 0)
+            .collect_vec();
+        let expected = sha256_bits_reference(&bits);
+        let input = bits.iter().map(|bit| *bit as u8).collect_vec();
+        let prover = mock_fn_circuit(
+            vec![input.clone()],
+            vec![],
+            synth_digest_bits,
+            bytes_to_instances(&expected),
+        );
+        assert_eq!(prover.verify(), Ok(()));
+
+        // within a byte, the bits (1, 0) and (0, 2) recombine to the same value, so only the
+        // boolean constraint rejects the 2.
+        let idx = (1..input.len())
+            .find(|&idx| idx % 8 != 0 && input[idx - 1] == 1 && input[idx] == 0)
+            .unwrap();
+        let mut non_boolean = input;
+        non_boolean[idx - 1] = 0;
+        non_boolean[idx] = 2;
+        let prover = mock_fn_circuit(
+            vec![non_boolean],
+            vec![],
+            synth_digest_bits,
+            bytes_to_instances(&expected),
+        );
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_digest_bits_byte_aligned() {
+        let msg = b"abc";
+        let bits = msg
+            .iter()
+            .flat_map(|byte| (0..8).rev().map(move |idx| (byte >> idx) & 1))
+            .collect_vec();
+        let expected = Sha256::digest(msg);
+        assert_eq!(
+            sha256_bits_reference(&bits.iter().map(|bit| *bit == 1).collect_vec()),
+            expected.to_vec()
+        );
+        let prover = mock_fn_circuit(
+            vec![bits],
+            vec![],
+            synth_digest_bits,
+            bytes_to_instances(&expected),
+        );
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    fn synth_digest_to_words<'v>(
+        sha256: &mut Sha256DynamicConfig<Fr>,
+        ctx: &mut Context<'v, Fr>,
+        inputs: &[Vec<u8>],
+    ) -> Result<Vec<AssignedValue<'v, Fr>>, Error> {
+        let gate = sha256.range().gate().clone();
+        // stands in for words produced by another gadget.
+        let external_words = inputs[1]
+            .chunks(4)
+            .map(|word| {
+                let word = u32::from_be_bytes(word.try_into().unwrap());
+                gate.load_witness(ctx, Value::known(Fr::from(word as u64)))
+            })
+            .collect_vec();
+        sha256.digest_to_words(ctx, &inputs[0], &external_words)?;
+        Ok(external_words)
+    }
+
+    #[test]
+    fn test_digest_to_words() {
+        fn to_words(digest: &[u8]) -> Vec<Fr> {
+            digest
+                .chunks(4)
+                .map(|word| Fr::from(u32::from_be_bytes(word.try_into().unwrap()) as u64))
+                .collect()
+        }
+        let input = b"linked to external cells".to_vec();
+        let digest = Sha256::digest(&input).to_vec();
+        let prover = mock_fn_circuit(
+            vec![input.clone(), digest.clone()],
+            vec![128],
+            synth_digest_to_words,
+            to_words(&digest),
+        );
+        assert_eq!(prover.verify(), Ok(()));
+
+        // the external words are public and consistent, but not the digest of `input`.
+        let mut wrong_digest = digest;
+        wrong_digest[31] ^= 1;
+        let prover = mock_fn_circuit(
+            vec![input, wrong_digest.clone()],
+            vec![128],
+            synth_digest_to_words,
+            to_words(&wrong_digest),
+        );
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_trust_input_bytes() {
+        let input = b"already range checked upstream";
+        let checked =
+            limb_bits_digest_stats::<8, { FnCircuit::NUM_SPREAD_COLUMNS }, false>(input, false);
+        let trusted =
+            limb_bits_digest_stats::<8, { FnCircuit::NUM_SPREAD_COLUMNS }, false>(input, true);
+        // only the input range checks are skipped.
+        assert_eq!(checked.spread_lookups, trusted.spread_lookups);
+        assert!(trusted.advice_cells < checked.advice_cells);
+        assert!(trusted.range_lookup_cells < checked.range_lookup_cells);
+    }
+
+    // Reduces the sum of five words (as in T1) with the carry witness in `inputs[1][0]`:
+    // 0 for the honest carry, 1 for a carry shifted by 2^-32 so the result is off by one.
+    fn synth_mod_u32_carry<'v>(
+        sha256: &mut Sha256DynamicConfig<Fr>,
+        ctx: &mut Context<'v, Fr>,
+        inputs: &[Vec<u8>],
+    ) -> Result<Vec<AssignedValue<'v, Fr>>, Error> {
+        let range = sha256.range().clone();
+        let words = inputs[0]
+            .chunks(4)
+            .map(|word| u32::from_be_bytes(word.try_into().unwrap()) as u64)
+            .collect_vec();
+        let sum: u64 = words.iter().sum();
+        let x = range.gate().load_witness(ctx, Value::known(Fr::from(sum)));
+        let mut hi = Fr::from(sum >> 32);
+        if inputs[1][0] == 1 {
+            use num_bigint::BigUint;
+            let modulus = modulus::<Fr>();
+            let two_pow_32 = BigUint::from(1u64 << 32);
+            hi += biguint_to_fe::<Fr>(&two_pow_32.modpow(&(&modulus - 2u64), &modulus));
+        }
+        let lo = compression::mod_u32_with_carry(ctx, &range, &x, Value::known(hi));
+        Ok(vec![lo])
+    }
+
+    #[test]
+    fn test_mod_u32_rejects_wrong_carry() {
+        let words = [
+            0xffff_ffffu32,
+            0xdead_beef,
+            0x8000_0001,
+            0x1234_5678,
+            0xcafe_babe,
+        ];
+        let input = words
+            .iter()
+            .flat_map(|word| word.to_be_bytes())
+            .collect_vec();
+        let sum: u64 = words.iter().map(|word| *word as u64).sum();
+        let lo = sum & 0xffff_ffff;
+
+        let prover = mock_fn_circuit(
+            vec![input.clone(), vec![0]],
+            vec![],
+            synth_mod_u32_carry,
+            vec![Fr::from(lo)],
+        );
+        assert_eq!(prover.verify(), Ok(()));
+
+        // the result `lo - 1` is a valid 32-bit value; only the carry range check catches it.
+        let prover = mock_fn_circuit(
+            vec![input, vec![1]],
+            vec![],
+            synth_mod_u32_carry,
+            vec![Fr::from(lo - 1)],
+        );
+        assert!(prover.verify().is_err());
+    }
+
+    fn synth_nullifier<'v>(
+        sha256: &mut Sha256DynamicConfig<Fr>,
+        ctx: &mut Context<'v, Fr>,
+        inputs: &[Vec<u8>],
+    ) -> Result<Vec<AssignedValue<'v, Fr>>, Error> {
+        let range = sha256.range().clone();
+        let secret = load_bytes(ctx, &range, &inputs[0]);
+        let external_nullifier = load_bytes(ctx, &range, &inputs[1]);
+        let nullifier = sha256.nullifier(ctx, &secret, &external_nullifier)?;
+        Ok(vec![nullifier])
+    }
+
+    fn nullifier_reference(secret: &[u8], external_nullifier: &[u8]) -> Fr {
+        let digest = Sha256::digest(&[secret, external_nullifier].concat());
+        biguint_to_fe(&num_bigint::BigUint::from_bytes_be(
+            &digest[..NULLIFIER_BYTES],
+        ))
+    }
+
+    #[test]
+    fn test_nullifier() {
+        let external_nullifier = b"epoch 7".to_vec();
+        let secrets = [vec![0x11; 32], vec![0x22; 32]];
+        let nullifiers = secrets
+            .iter()
+            .map(|secret| nullifier_reference(secret, &external_nullifier))
+            .collect_vec();
+        assert_ne!(nullifiers[0], nullifiers[1]);
+        for (secret, nullifier) in secrets.iter().zip(nullifiers.iter()) {
+            let prover = mock_fn_circuit(
+                vec![secret.clone(), external_nullifier.clone()],
+                vec![128],
+                synth_nullifier,
+                vec![*nullifier],
+            );
+            assert_eq!(prover.verify(), Ok(()));
+        }
+        // the nullifier of one secret does not verify for the other.
+        let prover = mock_fn_circuit(
+            vec![secrets[1].clone(), external_nullifier],
+            vec![128],
+            synth_nullifier,
+            vec![nullifiers[0]],
+        );
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_gate_accessor() {
+        fn synth<'v>(
+            sha256: &mut Sha256DynamicConfig<Fr>,
+            ctx: &mut Context<'v, Fr>,
+            inputs: &[Vec<u8>],
+        ) -> Result<Vec<AssignedValue<'v, Fr>>, Error> {
+            let result = sha256.digest(ctx, &inputs[0], None)?;
+            let sum = sha256.gate().add(
+                ctx,
+                QuantumCell::Existing(&result.output_bytes[0]),
+                QuantumCell::Existing(&result.output_bytes[31]),
+            );
+            Ok(vec![sum])
+        }
+        let input = b"abc".to_vec();
+        let digest = Sha256::digest(&input);
+        let public = vec![Fr::from(digest[0] as u64 + digest[31] as u64)];
+        let prover = mock_fn_circuit(vec![input], vec![64], synth, public);
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_aggregate_digests() {
+        fn synth<'v>(
+            sha256: &mut Sha256DynamicConfig<Fr>,
+            ctx: &mut Context<'v, Fr>,
+            inputs: &[Vec<u8>],
+        ) -> Result<Vec<AssignedValue<'v, Fr>>, Error> {
+            let mut digests = vec![];
+            for input in inputs.iter() {
+                digests.push(sha256.digest(ctx, input, None)?.output_bytes);
+            }
+            let commitment = sha256.aggregate_digests(ctx, &digests)?;
+            Ok(commitment.output_bytes)
+        }
+        let inputs = vec![
+            b"statement 0".to_vec(),
+            b"statement 1".to_vec(),
+            vec![0xa5; 70],
+            vec![],
+        ];
+        let digests = inputs
+            .iter()
+            .flat_map(|input| Sha256::digest(input).to_vec())
+            .collect_vec();
+        let public = bytes_to_instances(&Sha256::digest(&digests));
+        let prover = mock_fn_circuit(inputs, vec![64, 64, 128, 64, 192], synth, public);
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    // Deterministic pseudo-random messages for `seed`, each fitting `max_byte_size` once padded.
+    fn seeded_inputs(seed: u64, num_inputs: usize, max_byte_size: usize) -> Vec<Vec<u8>> {
+        use rand::{rngs::StdRng, SeedableRng};
+        let mut rng = StdRng::seed_from_u64(seed);
+        (0..num_inputs)
+            .map(|_| {
+                let len = rng.gen_range(0..=max_byte_size - 9);
+                (0..len).map(|_| rng.gen::<u8>()).collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_digest_seeded_fuzz() {
+        const NUM_INPUTS: usize = 3;
+        const MAX_BYTE_SIZE: usize = 128;
+        for seed in 0..8 {
+            let inputs = seeded_inputs(seed, NUM_INPUTS, MAX_BYTE_SIZE);
+            let expected = inputs
+                .iter()
+                .flat_map(|input| Sha256::digest(input).to_vec())
+                .collect_vec();
+            let prover = mock_fn_circuit(
+                inputs.clone(),
+                vec![MAX_BYTE_SIZE; NUM_INPUTS],
+                synth_digest_all,
+                bytes_to_instances(&expected),
+            );
+            assert_eq!(prover.verify(), Ok(()), "seed {} inputs {:?}", seed, inputs);
+        }
+    }
+
+    #[test]
+    fn test_commit_reveal() {
+        fn synth<'v>(
+            sha256: &mut Sha256DynamicConfig<Fr>,
+            ctx: &mut Context<'v, Fr>,
+            inputs: &[Vec<u8>],
+        ) -> Result<Vec<AssignedValue<'v, Fr>>, Error> {
+            let midstate = sha256.commit(ctx, &inputs[0])?;
+            let result = sha256.reveal(ctx, &midstate, &inputs[1], inputs[0].len())?;
+            Ok([midstate, result.output_bytes].concat())
+        }
+        let prefix = (0..128).map(|idx| (3 * idx + 1) as u8).collect_vec();
+        let suffix = b"revealed suffix".to_vec();
+        let expected_midstate = compress_block(midstate(&prefix[..64]), block_words(&prefix[64..]));
+        let expected = Sha256::digest(&[prefix.clone(), suffix.clone()].concat());
+        let public = expected_midstate
+            .iter()
+            .map(|word| Fr::from(*word as u64))
+            .chain(bytes_to_instances(&expected))
+            .collect_vec();
+        let prover = mock_fn_circuit(vec![prefix, suffix], vec![64], synth, public);
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_bytes_iter() {
+        fn synth<'v>(
+            sha256: &mut Sha256DynamicConfig<Fr>,
+            ctx: &mut Context<'v, Fr>,
+            inputs: &[Vec<u8>],
+        ) -> Result<Vec<AssignedValue<'v, Fr>>, Error> {
+            let result = sha256.digest(ctx, &inputs[0], None)?;
+            assert_eq!(result.bytes_iter().count(), 32);
+            let gate = sha256.gate();
+            let mut sum = gate.load_zero(ctx);
+            for byte in result.bytes_iter() {
+                sum = gate.add(
+                    ctx,
+                    QuantumCell::Existing(&sum),
+                    QuantumCell::Existing(byte),
+                );
+            }
+            Ok(vec![sum])
+        }
+        let input = b"abc".to_vec();
+        let sum: u64 = Sha256::digest(&input).iter().map(|byte| *byte as u64).sum();
+        let prover = mock_fn_circuit(vec![input], vec![64], synth, vec![Fr::from(sum)]);
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    fn synth_digest_var_len<'v>(
+        sha256: &mut Sha256DynamicConfig<Fr>,
+        ctx: &mut Context<'v, Fr>,
+        inputs: &[Vec<u8>],
+    ) -> Result<Vec<AssignedValue<'v, Fr>>, Error> {
+        let first = sha256.digest(ctx, &inputs[0], None)?;
+        // the second message is as long as the first digest byte says.
+        let len = first.output_bytes[0].clone();
+        let second = sha256.digest_var_len(ctx, &inputs[1], &len)?;
+        Ok(second.output_bytes)
+    }
+
+    #[test]
+    fn test_digest_var_len() {
+        let seed = b"vrf output".to_vec();
+        let len = Sha256::digest(&seed)[0] as usize;
+        let message = (0..len).map(|idx| idx as u8).collect_vec();
+        let public = bytes_to_instances(&Sha256::digest(&message));
+        let prover = mock_fn_circuit(
+            vec![seed.clone(), message.clone()],
+            vec![64, 320],
+            synth_digest_var_len,
+            public.clone(),
+        );
+        assert_eq!(prover.verify(), Ok(()));
+
+        // a message one byte shorter than the derived length is rejected.
+        let mut short_message = message;
+        short_message.pop();
+        let prover = mock_fn_circuit(
+            vec![seed, short_message.clone()],
+            vec![64, 320],
+            synth_digest_var_len,
+            bytes_to_instances(&Sha256::digest(&short_message)),
+        );
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_message_blocks() {
+        let msg = (0..70).map(|idx| idx as u8).collect_vec();
+        let mut manual = vec![[0u32; 16]; 2];
+        for (idx, byte) in msg.iter().enumerate() {
+            manual[idx / 64][(idx % 64) / 4] |= (*byte as u32) << (24 - 8 * (idx % 4));
+        }
+        // the 0x80 terminator follows byte 69, the bit length 560 ends the second block.
+        manual[1][70 % 64 / 4] |= 0x80 << (24 - 8 * (70 % 4));
+        manual[1][15] = 8 * 70;
+        assert_eq!(message_blocks(&msg), manual);
+        assert_eq!(message_blocks(b"").len(), 1);
+        assert_eq!(message_blocks(&[0; 55]).len(), 1);
+        assert_eq!(message_blocks(&[0; 56]).len(), 2);
+        // compressing the blocks reproduces the digest.
+        let state = message_blocks(&msg)
+            .into_iter()
+            .fold(INIT_STATE, compress_block);
+        let digest = state
+            .iter()
+            .flat_map(|word| word.to_be_bytes())
+            .collect_vec();
+        assert_eq!(digest, Sha256::digest(&msg).to_vec());
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "byte 5: witness")]
+    fn test_assert_digest_reports_corrupted_witness() {
+        fn synth<'v>(
+            sha256: &mut Sha256DynamicConfig<Fr>,
+            ctx: &mut Context<'v, Fr>,
+            inputs: &[Vec<u8>],
+        ) -> Result<Vec<AssignedValue<'v, Fr>>, Error> {
+            let mut result = sha256.digest(ctx, &inputs[0], None)?;
+            result.assert_digest(&inputs[0]);
+            let corrupted = result.output_bytes[5].value().map(|v| *v + Fr::from(1u64));
+            result.output_bytes[5] = sha256.gate().load_witness(ctx, corrupted);
+            result.assert_digest(&inputs[0]);
+            Ok(vec![])
+        }
+        mock_fn_circuit(vec![b"abc".to_vec()], vec![64], synth, vec![]);
+    }
+
+    #[test]
+    fn test_digest_blocks_mixed_visibility() {
+        fn synth<'v>(
+            sha256: &mut Sha256DynamicConfig<Fr>,
+            ctx: &mut Context<'v, Fr>,
+            inputs: &[Vec<u8>],
+        ) -> Result<Vec<AssignedValue<'v, Fr>>, Error> {
+            let visibilities = [
+                BlockVisibility::Public,
+                BlockVisibility::Private,
+                BlockVisibility::Public,
+            ];
+            let blocks = inputs[0]
+                .chunks(64)
+                .zip(visibilities.iter())
+                .map(|(block, visibility)| (<[u8; 64]>::try_from(block).unwrap(), *visibility))
+                .collect_vec();
+            let (_, digest) = sha256.digest_blocks(ctx, &blocks)?;
+            Ok(digest)
+        }
+        // header, private body, public trailer.
+        let document = [vec![0x48; 64], vec![0x9c; 64], vec![0x54; 64]].concat();
+        let public = bytes_to_instances(&Sha256::digest(&document));
+        let prover = mock_fn_circuit(vec![document], vec![], synth, public);
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_digest_rlp() {
+        fn synth<'v>(
+            sha256: &mut Sha256DynamicConfig<Fr>,
+            ctx: &mut Context<'v, Fr>,
+            inputs: &[Vec<u8>],
+        ) -> Result<Vec<AssignedValue<'v, Fr>>, Error> {
+            let range = sha256.range().clone();
+            let encoded = load_bytes(ctx, &range, &inputs[0]);
+            Ok(sha256.digest_rlp(ctx, &encoded)?.output_bytes)
+        }
+        assert_eq!(rlp_encoded_len(3), 4);
+        assert_eq!(rlp_encoded_len(55), 56);
+        assert_eq!(rlp_encoded_len(56), 58);
+        assert_eq!(rlp_encoded_len(1024), 1027);
+        assert_eq!(rlp_max_byte_size(46), 64);
+        assert_eq!(rlp_max_byte_size(55), 128);
+        // ["cat", "dog", 100 zero bytes]
+        let mut blob = hex::decode("f86e83636174836467").unwrap();
+        blob.push(b'g');
+        blob.extend_from_slice(&[0xb8, 100]);
+        blob.extend_from_slice(&[0; 100]);
+        assert_eq!(blob.len(), rlp_encoded_len(0x6e));
+        let public = bytes_to_instances(&Sha256::digest(&blob));
+        let prover = mock_fn_circuit(vec![blob], vec![rlp_max_byte_size(0x6e)], synth, public);
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_digest_output_words_and_bytes() {
+        fn synth<'v>(
+            sha256: &mut Sha256DynamicConfig<Fr>,
+            ctx: &mut Context<'v, Fr>,
+            inputs: &[Vec<u8>],
+        ) -> Result<Vec<AssignedValue<'v, Fr>>, Error> {
+            let result = sha256.digest(ctx, &inputs[0], None)?;
+            Ok([result.output_words, result.output_bytes].concat())
+        }
+        let input = vec![0x3c; 100];
+        let digest = Sha256::digest(&input);
+        let mut words = digest
+            .chunks(4)
+            .map(|word| Fr::from(u32::from_be_bytes(word.try_into().unwrap()) as u64))
+            .collect_vec();
+        let public = [words.clone(), bytes_to_instances(&digest)].concat();
+        let prover = mock_fn_circuit(vec![input.clone()], vec![128], synth, public);
+        assert_eq!(prover.verify(), Ok(()));
+
+        // the words are the ones the bytes decompose, so reordering them is rejected.
+        words.swap(0, 1);
+        let public = [words, bytes_to_instances(&digest)].concat();
+        let prover = mock_fn_circuit(vec![input], vec![128], synth, public);
+        assert!(prover.verify().is_err());
+    }
+
+    fn synth_digest_with_round_constants<'v>(
+        sha256: &mut Sha256DynamicConfig<Fr>,
+        ctx: &mut Context<'v, Fr>,
+        inputs: &[Vec<u8>],
+    ) -> Result<Vec<AssignedValue<'v, Fr>>, Error> {
+        let mut round_constants = ROUND_CONSTANTS;
+        if inputs[1][0] == 1 {
+            round_constants.reverse();
+        }
+        sha256.set_round_constants(round_constants);
+        Ok(sha256.digest(ctx, &inputs[0], None)?.output_bytes)
+    }
+
+    #[test]
+    fn test_custom_round_constants() {
+        let input = b"tweaked".to_vec();
+        let prover = mock_fn_circuit(
+            vec![input.clone(), vec![0]],
+            vec![64],
+            synth_digest_with_round_constants,
+            bytes_to_instances(&Sha256::digest(&input)),
+        );
+        assert_eq!(prover.verify(), Ok(()));
+
+        let mut reversed = ROUND_CONSTANTS;
+        reversed.reverse();
+        let state = message_blocks(&input)
+            .into_iter()
+            .fold(INIT_STATE, |state, block| {
+                compress_block_with_round_constants(state, block, &reversed)
+            });
+        let expected = state
+            .iter()
+            .flat_map(|word| word.to_be_bytes())
+            .collect_vec();
+        assert_ne!(expected, Sha256::digest(&input).to_vec());
+        let prover = mock_fn_circuit(
+            vec![input, vec![1]],
+            vec![64],
+            synth_digest_with_round_constants,
+            bytes_to_instances(&expected),
+        );
+        assert_eq!(prover.verify(), Ok(()));
     }
 
-    pub fn range(&self) -> &RangeConfig<F> {
-        &self.range
+    fn synth_preimage_length<'v>(
+        sha256: &mut Sha256DynamicConfig<Fr>,
+        ctx: &mut Context<'v, Fr>,
+        inputs: &[Vec<u8>],
+    ) -> Result<Vec<AssignedValue<'v, Fr>>, Error> {
+        let result = sha256.prove_membership_of_preimage_length(ctx, &inputs[0], 32, 64)?;
+        Ok(result.output_bytes)
     }
 
-    pub fn load(&self, layouter: &mut impl Layouter<F>) -> Result<(), Error> {
-        self.spread_config.load(layouter)
+    #[test]
+    fn test_preimage_length_in_range() {
+        for len in [32, 40, 64] {
+            let preimage = vec![0x7e; len];
+            let prover = mock_fn_circuit(
+                vec![preimage.clone()],
+                vec![128],
+                synth_preimage_length,
+                bytes_to_instances(&Sha256::digest(&preimage)),
+            );
+            assert_eq!(prover.verify(), Ok(()), "len {}", len);
+        }
+        for len in [31, 65] {
+            let preimage = vec![0x7e; len];
+            let prover = mock_fn_circuit(
+                vec![preimage.clone()],
+                vec![128],
+                synth_preimage_length,
+                bytes_to_instances(&Sha256::digest(&preimage)),
+            );
+            assert!(prover.verify().is_err(), "len {}", len);
+        }
     }
-}
-
-#[cfg(test)]
-mod test {
-    use std::marker::PhantomData;
-
-    use super::*;
-    use halo2_base::halo2_proofs::{
-        circuit::{Cell, Layouter, Region, SimpleFloorPlanner},
-        dev::MockProver,
-        halo2curves::bn256::Fr,
-        plonk::{Circuit, ConstraintSystem, Instance},
-    };
-    use halo2_base::{gates::range::RangeStrategy::Vertical, ContextParams, SKIP_FIRST_PASS};
 
-    use num_bigint::RandomBits;
-    use rand::rngs::OsRng;
-    use rand::{thread_rng, Rng};
+    #[test]
+    fn test_bench_circuit() {
+        let msgs = vec![vec![], vec![0x42; 55], vec![0x42; 100]];
+        let circuit = bench::build_sha_circuit::<18>(&msgs).unwrap();
+        assert_eq!(circuit.instances()[0].len(), 32 * msgs.len());
+        assert_eq!(circuit.mock_prove(), Ok(()));
 
-    #[derive(Debug, Clone)]
-    struct TestConfig<F: PrimeField> {
-        sha256: Sha256DynamicConfig<F>,
-        hash_column: Column<Instance>,
+        let empty = circuit.without_witnesses();
+        assert_eq!(empty.instances()[0].len(), 0);
+        assert!(bench::build_sha_circuit::<16>(&msgs).is_err());
     }
 
-    #[derive(Debug, Clone)]
-    struct TestCircuit<F: PrimeField> {
-        test_inputs: Vec<Vec<u8>>,
-        precomputed_input_lens: Vec<usize>,
-        _f: PhantomData<F>,
+    #[test]
+    fn test_sha256_block_multiples() {
+        // a whole number of blocks still needs a full extra block for the padding.
+        let inputs = [64, 128, 192]
+            .iter()
+            .map(|len| (0..*len).map(|idx| (idx * 13 + 5) as u8).collect_vec())
+            .collect_vec();
+        for input in inputs.iter() {
+            assert_eq!(num_blocks_for_len(input.len()), input.len() / 64 + 1);
+        }
+        let expected = inputs
+            .iter()
+            .flat_map(|input| Sha256::digest(input).to_vec())
+            .collect_vec();
+        let prover = mock_fn_circuit(
+            inputs,
+            vec![128, 192, 256],
+            synth_digest_all,
+            bytes_to_instances(&expected),
+        );
+        assert_eq!(prover.verify(), Ok(()));
     }
 
-    impl<F: PrimeField> Circuit<F> for TestCircuit<F> {
-        type Config = TestConfig<F>;
-        type FloorPlanner = SimpleFloorPlanner;
-
-        fn without_witnesses(&self) -> Self {
-            unimplemented!()
+    #[test]
+    fn test_spread_limb_positions() {
+        for num_columns in [1, 4, 16] {
+            let positions = (0..3 * num_columns)
+                .map(|idx| spread::limb_position(idx, num_columns))
+                .collect_vec();
+            let expected = (0..3)
+                .flat_map(|row| (0..num_columns).map(move |column| (column, row)))
+                .collect_vec();
+            assert_eq!(positions, expected);
         }
+    }
 
-        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
-            let range_config = RangeConfig::configure(
-                meta,
-                Vertical,
-                &[Self::NUM_ADVICE],
-                &[Self::NUM_LOOKUP_ADVICE],
-                Self::NUM_FIXED,
-                Self::LOOKUP_BITS,
-                0,
-                17,
-            );
-            let hash_column = meta.instance_column();
-            meta.enable_equality(hash_column);
-            let sha256: Sha256DynamicConfig<F> = Sha256DynamicConfig::configure(
-                meta,
-                vec![Self::MAX_BYTE_SIZE1, Self::MAX_BYTE_SIZE2],
-                range_config,
-                8,
-                2,
-                true,
-            );
-            Self::Config {
-                sha256,
-                hash_column,
+    #[test]
+    fn test_spread_column_counts() {
+        let input = (0..100).map(|idx| (idx * 29) as u8).collect_vec();
+        // every column count proves the same digest with the same lookups.
+        let lookups_1 = spread_circuit_lookups::<8, 1>(&input);
+        let lookups_4 = spread_circuit_lookups::<8, 4>(&input);
+        let lookups_16 = spread_circuit_lookups::<8, 16>(&input);
+        assert_eq!(lookups_1, lookups_4);
+        assert_eq!(lookups_1, lookups_16);
+    }
+
+    #[test]
+    fn test_digest_sparse() {
+        fn synth<'v>(
+            sha256: &mut Sha256DynamicConfig<Fr>,
+            ctx: &mut Context<'v, Fr>,
+            inputs: &[Vec<u8>],
+        ) -> Result<Vec<AssignedValue<'v, Fr>>, Error> {
+            let dense = &inputs[0];
+            let entries = dense
+                .iter()
+                .enumerate()
+                .filter(|(_, byte)| **byte != 0)
+                .map(|(position, byte)| (position, *byte))
+                .collect_vec();
+            let advice_cells = ctx.total_advice;
+            let sparse_digest = sha256.digest_sparse(ctx, dense.len(), &entries)?;
+            let sparse_cells = ctx.total_advice - advice_cells;
+            let (dense_result, dense_stats) = sha256.digest_with_stats(ctx, dense, None)?;
+            assert!(sparse_cells < dense_stats.advice_cells);
+            for (sparse, dense) in sparse_digest.iter().zip(dense_result.output_bytes.iter()) {
+                sha256.gate().assert_equal(
+                    ctx,
+                    QuantumCell::Existing(sparse),
+                    QuantumCell::Existing(dense),
+                );
             }
+            Ok(sparse_digest)
         }
+        let mut dense = vec![0u8; 150];
+        dense[3] = 0x11;
+        dense[64] = 0xfe;
+        dense[149] = 0x80;
+        let public = bytes_to_instances(&Sha256::digest(&dense));
+        let prover = mock_fn_circuit(vec![dense.clone()], vec![192], synth, public);
+        assert_eq!(prover.verify(), Ok(()));
 
-        fn synthesize(
-            &self,
-            config: Self::Config,
-            mut layouter: impl Layouter<F>,
-        ) -> Result<(), Error> {
-            let mut sha256 = config.sha256.clone();
-            let range = sha256.range().clone();
-            sha256.range().load_lookup_table(&mut layouter)?;
-            sha256.load(&mut layouter)?;
-            let mut first_pass = SKIP_FIRST_PASS;
-            let mut assigned_hash_cells = vec![];
-            layouter.assign_region(
-                || "dynamic sha2 test",
-                |region| {
-                    if first_pass {
-                        first_pass = false;
-                        return Ok(());
-                    }
+        // the positions are constants, so the same values at other positions give another digest.
+        let mut shifted = vec![0u8; 150];
+        shifted[4] = 0x11;
+        shifted[65] = 0xfe;
+        shifted[148] = 0x80;
+        let public = bytes_to_instances(&Sha256::digest(&shifted));
+        let prover = mock_fn_circuit(vec![dense], vec![192], synth, public);
+        assert!(prover.verify().is_err());
+    }
 
-                    let ctx = &mut sha256.new_context(region);
-                    let result0 = sha256.digest(
-                        ctx,
-                        &self.test_inputs[0],
-                        Some(self.precomputed_input_lens[0]),
-                    )?;
-                    assigned_hash_cells
-                        .append(&mut result0.output_bytes.into_iter().map(|v| v.cell()).collect());
-                    let result1 = sha256.digest(
-                        ctx,
-                        &self.test_inputs[1],
-                        Some(self.precomputed_input_lens[1]),
-                    )?;
-                    assigned_hash_cells
-                        .append(&mut result1.output_bytes.into_iter().map(|v| v.cell()).collect());
-                    range.finalize(ctx);
-                    {
-                        println!("total advice cells: {}", ctx.total_advice);
-                        let const_rows = ctx.total_fixed + 1;
-                        println!("maximum rows used by a fixed column: {const_rows}");
-                        println!("lookup cells used: {}", ctx.cells_to_lookup.len());
-                    }
-                    Ok(())
-                },
-            )?;
-            for (idx, hash) in assigned_hash_cells.into_iter().enumerate() {
-                layouter.constrain_instance(hash, config.hash_column, idx)?;
-            }
-            Ok(())
+    // Hashes `inputs[0]` after overwriting the padded byte at `inputs[1][0]` with `inputs[1][1]`.
+    fn synth_tampered_padding<'v>(
+        sha256: &mut Sha256DynamicConfig<Fr>,
+        ctx: &mut Context<'v, Fr>,
+        inputs: &[Vec<u8>],
+    ) -> Result<Vec<AssignedValue<'v, Fr>>, Error> {
+        let mut padded_inputs = sha256.pad_inputs(&inputs[0], 0);
+        if let [idx, value] = inputs[1][..] {
+            padded_inputs[idx as usize] = value;
         }
+        let gate = sha256.range().gate().clone();
+        let init_state = INIT_STATE
+            .iter()
+            .map(|h| gate.load_constant(ctx, Fr::from(*h as u64)))
+            .collect_vec();
+        sha256.digest_with_state(ctx, &padded_inputs, inputs[0].len(), 0, init_state)?;
+        Ok(vec![])
     }
 
-    impl<F: PrimeField> TestCircuit<F> {
-        const MAX_BYTE_SIZE1: usize = 128;
-        const MAX_BYTE_SIZE2: usize = 128;
-        const NUM_ADVICE: usize = 3;
-        const NUM_FIXED: usize = 1;
-        const NUM_LOOKUP_ADVICE: usize = 1;
-        const LOOKUP_BITS: usize = 16;
+    // Claims `inputs[0]` fills all but the last byte, which holds the terminator, leaving no room
+    // for the length field.
+    fn synth_overlong_padding<'v>(
+        sha256: &mut Sha256DynamicConfig<Fr>,
+        ctx: &mut Context<'v, Fr>,
+        inputs: &[Vec<u8>],
+    ) -> Result<Vec<AssignedValue<'v, Fr>>, Error> {
+        let padded_inputs = [&inputs[0][..], &[0x80]].concat();
+        let gate = sha256.range().gate().clone();
+        let init_state = INIT_STATE
+            .iter()
+            .map(|h| gate.load_constant(ctx, Fr::from(*h as u64)))
+            .collect_vec();
+        let result =
+            sha256.digest_with_state(ctx, &padded_inputs, inputs[0].len(), 0, init_state)?;
+        Ok(result.output_bytes)
     }
 
     #[test]
-    fn test_sha256_correct1() {
-        let k = 17;
+    fn test_tampered_padding() {
+        let input = b"padding".to_vec();
+        let prover = mock_fn_circuit(
+            vec![input.clone(), vec![]],
+            vec![128],
+            synth_tampered_padding,
+            vec![],
+        );
+        assert_eq!(prover.verify(), Ok(()));
 
-        // Test vector: "abc"
-        let test_input = vec!['a' as u8, 'b' as u8, 'c' as u8];
+        // the terminator, a zero before the length field and a byte of the unused trailing block.
+        for &(idx, value) in [(7u8, 0x00u8), (20, 0x01), (100, 0x01)].iter() {
+            let prover = mock_fn_circuit(
+                vec![input.clone(), vec![idx, value]],
+                vec![128],
+                synth_tampered_padding,
+                vec![],
+            );
+            assert!(prover.verify().is_err());
+        }
 
-        let circuit = TestCircuit::<Fr> {
-            test_inputs: vec![test_input, vec![]],
-            precomputed_input_lens: vec![0, 0],
-            _f: PhantomData,
-        };
-        let test_output0: [u8; 32] = [
-            0b10111010, 0b01111000, 0b00010110, 0b10111111, 0b10001111, 0b00000001, 0b11001111,
-            0b11101010, 0b01000001, 0b01000001, 0b01000000, 0b11011110, 0b01011101, 0b10101110,
-            0b00100010, 0b00100011, 0b10110000, 0b00000011, 0b01100001, 0b10100011, 0b10010110,
-            0b00010111, 0b01111010, 0b10011100, 0b10110100, 0b00010000, 0b11111111, 0b01100001,
-            0b11110010, 0b00000000, 0b00010101, 0b10101101,
-        ];
-        let test_output1 =
-            hex::decode("e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855")
-                .unwrap();
-        let test_output = vec![test_output0.to_vec(), test_output1]
-            .concat()
-            .into_iter()
-            .map(|val| Fr::from_u128(val as u128))
-            .collect();
-        let public_inputs = vec![test_output];
+        // a length pointing past the last block would select no state and output zeros.
+        let prover = mock_fn_circuit(
+            vec![vec![0x61; 127]],
+            vec![128],
+            synth_overlong_padding,
+            vec![Fr::zero(); 32],
+        );
+        assert!(prover.verify().is_err());
+    }
 
-        let prover = MockProver::run(k, &circuit, public_inputs).unwrap();
-        assert_eq!(prover.verify(), Ok(()));
+    // Assigns `values` to an advice column and binds them to the instance `rows`.
+    #[derive(Clone)]
+    struct InstanceRowsCircuit {
+        values: Vec<u64>,
+        rows: Range<usize>,
     }
 
-    #[test]
-    fn test_sha256_correct2() {
-        let k = 17;
+    impl Circuit<Fr> for InstanceRowsCircuit {
+        type Config = (Column<Advice>, Column<Instance>);
+        type FloorPlanner = SimpleFloorPlanner;
 
-        // Test vector: "0x0"
-        let test_input = vec![0u8];
+        fn without_witnesses(&self) -> Self {
+            unimplemented!()
+        }
 
-        let circuit = TestCircuit::<Fr> {
-            test_inputs: vec![test_input, vec![]],
-            precomputed_input_lens: vec![0, 0],
-            _f: PhantomData,
-        };
-        let test_output0 =
-            hex::decode("6e340b9cffb37a989ca544e6bb780a2c78901d3fb33738768511a30617afa01d")
-                .unwrap();
-        let test_output1 =
-            hex::decode("e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855")
-                .unwrap();
-        let test_output = vec![test_output0, test_output1]
-            .concat()
-            .into_iter()
-            .map(|val| Fr::from_u128(val as u128))
-            .collect();
-        let public_inputs = vec![test_output];
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            let advice = meta.advice_column();
+            let instance = meta.instance_column();
+            meta.enable_equality(advice);
+            meta.enable_equality(instance);
+            (advice, instance)
+        }
 
-        let prover = MockProver::run(k, &circuit, public_inputs).unwrap();
-        assert_eq!(prover.verify(), Ok(()));
+        fn synthesize(
+            &self,
+            (advice, instance): Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            let cells = layouter.assign_region(
+                || "values",
+                |mut region| {
+                    self.values
+                        .iter()
+                        .enumerate()
+                        .map(|(offset, value)| {
+                            region
+                                .assign_advice(
+                                    || "value",
+                                    advice,
+                                    offset,
+                                    || Value::known(Fr::from(*value)),
+                                )
+                                .map(|cell| cell.cell())
+                        })
+                        .collect::<Result<Vec<_>, Error>>()
+                },
+            )?;
+            constrain_cells_to_instance_rows(&mut layouter, &cells, instance, self.rows.clone())
+        }
     }
 
     #[test]
-    fn test_sha256_correct3() {
-        let k = 17;
-
-        let test_inputs = vec![vec![0x1; 56], vec![0u8, 0u8, 0u8]];
-
-        let circuit = TestCircuit::<Fr> {
-            test_inputs,
-            precomputed_input_lens: vec![0, 0],
-            _f: PhantomData,
+    fn test_constrain_cells_to_instance_rows() {
+        let circuit = InstanceRowsCircuit {
+            values: vec![7, 9],
+            rows: 2..4,
         };
-        let test_output0 =
-            hex::decode("51e14a913680f24c85fe3b0e2e5b57f7202f117bb214f8ffdd4ea0f4e921fd52")
-                .unwrap();
-        let test_output1 =
-            hex::decode("709e80c88487a2411e1ee4dfb9f22a861492d20c4765150c0c794abd70f8147c")
-                .unwrap();
-        let test_output = vec![test_output0, test_output1]
-            .concat()
-            .into_iter()
-            .map(|val| Fr::from_u128(val as u128))
-            .collect();
-        let public_inputs = vec![test_output];
-
-        let prover = MockProver::run(k, &circuit, public_inputs).unwrap();
+        let public = vec![Fr::zero(), Fr::zero(), Fr::from(7), Fr::from(9)];
+        let prover = MockProver::run(4, &circuit, vec![public]).unwrap();
         assert_eq!(prover.verify(), Ok(()));
-    }
 
-    #[test]
-    fn test_sha256_correct4() {
-        let k = 17;
+        let shifted = vec![Fr::zero(), Fr::from(7), Fr::from(9), Fr::zero()];
+        let prover = MockProver::run(4, &circuit, vec![shifted]).unwrap();
+        assert!(prover.verify().is_err());
 
-        fn gen_random_bytes(len: usize) -> Vec<u8> {
-            let mut rng = thread_rng();
-            (0..len).map(|_| rng.gen::<u8>()).collect()
-        }
-        let test_inputs = vec![gen_random_bytes(128 + 64), gen_random_bytes(128 + 64)];
-        let test_output0 = Sha256::digest(&test_inputs[0]);
-        let test_output1 = Sha256::digest(&test_inputs[1]);
-        let circuit = TestCircuit::<Fr> {
-            test_inputs,
-            precomputed_input_lens: vec![128, 128],
-            _f: PhantomData,
+        // one row more than there are cells is rejected during synthesis.
+        let circuit = InstanceRowsCircuit {
+            values: vec![7, 9],
+            rows: 2..5,
         };
-        let test_output = vec![test_output0, test_output1]
-            .concat()
-            .into_iter()
-            .map(|val| Fr::from_u128(val as u128))
-            .collect();
-        let public_inputs = vec![test_output];
-
-        let prover = MockProver::run(k, &circuit, public_inputs).unwrap();
-        assert_eq!(prover.verify(), Ok(()));
+        let public = vec![Fr::zero(), Fr::zero(), Fr::from(7), Fr::from(9), Fr::zero()];
+        assert!(MockProver::run(4, &circuit, vec![public]).is_err());
     }
 }
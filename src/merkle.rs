@@ -0,0 +1,87 @@
+use crate::{AssignedHashResult, Block, Sha256DynamicConfig};
+use halo2_base::halo2_proofs::plonk::Error;
+use halo2_base::QuantumCell;
+use halo2_base::{
+    gates::{GateInstructions, RangeInstructions},
+    utils::PrimeField,
+    AssignedValue, Context,
+};
+use itertools::Itertools;
+
+impl<F: PrimeField> Sha256DynamicConfig<F> {
+    /// Hashes `leaf` up a Merkle branch with `digest_block` and constrains the result to `root`.
+    /// `path[i]` is the 32-byte sibling at depth `i` (leaf level first) and `index_bits[i]` is 1
+    /// if the current node is the right child at that depth, i.e. the little-endian leaf index.
+    /// The sibling bytes are range checked here; `leaf` is expected to be range-checked bytes.
+    pub fn verify_merkle_path<'b>(
+        &mut self,
+        ctx: &mut Context<'b, F>,
+        leaf: &[AssignedValue<'b, F>],
+        path: &[Vec<AssignedValue<'b, F>>],
+        index_bits: &[AssignedValue<'b, F>],
+        root: &[AssignedValue<'b, F>],
+    ) -> Result<(), Error> {
+        assert_eq!(leaf.len(), 32);
+        assert_eq!(root.len(), 32);
+        assert_eq!(path.len(), index_bits.len());
+        let range = self.range().clone();
+        let gate = range.gate();
+        let mut node = leaf.to_vec();
+        for (sibling, bit) in path.iter().zip(index_bits.iter()) {
+            assert_eq!(sibling.len(), 32);
+            for byte in sibling.iter() {
+                range.range_check(ctx, byte, 8);
+            }
+            let bit_squared = gate.mul(ctx, QuantumCell::Existing(bit), QuantumCell::Existing(bit));
+            gate.assert_equal(
+                ctx,
+                QuantumCell::Existing(&bit_squared),
+                QuantumCell::Existing(bit),
+            );
+            let (left, right): (Vec<_>, Vec<_>) = node
+                .iter()
+                .zip(sibling.iter())
+                .map(|(cur, sib)| {
+                    let left = gate.select(
+                        ctx,
+                        QuantumCell::Existing(sib),
+                        QuantumCell::Existing(cur),
+                        QuantumCell::Existing(bit),
+                    );
+                    let right = gate.select(
+                        ctx,
+                        QuantumCell::Existing(cur),
+                        QuantumCell::Existing(sib),
+                        QuantumCell::Existing(bit),
+                    );
+                    (left, right)
+                })
+                .unzip();
+            node = self.digest_block(ctx, &Block::from_halves(&left, &right))?;
+        }
+        for (computed, expected) in node.iter().zip_eq(root.iter()) {
+            gate.assert_equal(
+                ctx,
+                QuantumCell::Existing(computed),
+                QuantumCell::Existing(expected),
+            );
+        }
+        Ok(())
+    }
+
+    /// Proves knowledge of `data` whose digest is the leaf at the position given by `index_bits`
+    /// in the tree with the given `root`, i.e. `verify_merkle_path(SHA256(data), ..)`.
+    /// Returns the digest of `data`, whose `input_bytes` can be constrained further.
+    pub fn verify_merkle_leaf<'a, 'b: 'a>(
+        &'a mut self,
+        ctx: &mut Context<'b, F>,
+        data: &'a [u8],
+        path: &[Vec<AssignedValue<'b, F>>],
+        index_bits: &[AssignedValue<'b, F>],
+        root: &[AssignedValue<'b, F>],
+    ) -> Result<AssignedHashResult<'b, F>, Error> {
+        let leaf = self.digest(ctx, data, None)?;
+        self.verify_merkle_path(ctx, &leaf.output_bytes, path, index_bits, root)?;
+        Ok(leaf)
+    }
+}
@@ -0,0 +1,30 @@
+use crate::{compose_be_bytes, Sha256DynamicConfig};
+use halo2_base::halo2_proofs::plonk::Error;
+use halo2_base::{utils::PrimeField, AssignedValue, Context};
+
+/// Number of leading digest bytes packed into a nullifier, so that it fits any field of at
+/// least 248 bits without reduction.
+pub const NULLIFIER_BYTES: usize = 31;
+
+impl<F: PrimeField> Sha256DynamicConfig<F> {
+    /// Computes the nullifier `SHA256(secret || external_nullifier) >> 8`, i.e. the first 31
+    /// digest bytes as a big-endian field element. The same `secret` gives unlinkable
+    /// nullifiers for different `external_nullifier`s (e.g. per-epoch or per-application
+    /// tags). Both inputs must be range-checked bytes; the caller exposes the result, e.g.
+    /// with `constrain_cells_to_instance`.
+    pub fn nullifier<'b>(
+        &mut self,
+        ctx: &mut Context<'b, F>,
+        secret: &[AssignedValue<'b, F>],
+        external_nullifier: &[AssignedValue<'b, F>],
+    ) -> Result<AssignedValue<'b, F>, Error> {
+        let input_bytes = [secret, external_nullifier].concat();
+        let result = self.digest_assigned_bytes(ctx, &input_bytes)?;
+        let gate = self.range().gate();
+        Ok(compose_be_bytes(
+            ctx,
+            gate,
+            &result.output_bytes[..NULLIFIER_BYTES],
+        ))
+    }
+}
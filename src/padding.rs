@@ -0,0 +1,34 @@
+const BLOCK_BYTE_SIZE: usize = 64;
+// the 0x80 terminator byte and the 8-byte big-endian bit length.
+const MIN_PADDING_BYTE_SIZE: usize = 9;
+
+/// Returns the number of 512-bit blocks a `len`-byte message occupies after SHA-256 padding.
+pub fn num_blocks_for_len(len: usize) -> usize {
+    (len + MIN_PADDING_BYTE_SIZE + BLOCK_BYTE_SIZE - 1) / BLOCK_BYTE_SIZE
+}
+
+/// Applies SHA-256 padding to `msg`: a 0x80 byte, zeros up to 56 mod 64, and the 64-bit
+/// big-endian bit length. This is the prefix of the bytes `Sha256DynamicConfig::digest` assigns.
+pub fn pad_message(msg: &[u8]) -> Vec<u8> {
+    let padded_len = num_blocks_for_len(msg.len()) * BLOCK_BYTE_SIZE;
+    let mut padded = msg.to_vec();
+    padded.push(0x80);
+    padded.resize(padded_len - 8, 0);
+    padded.extend_from_slice(&(8 * msg.len() as u64).to_be_bytes());
+    padded
+}
+
+/// The big-endian 16-word blocks of the padded `msg`, i.e. exactly the blocks `digest`
+/// compresses before the zero blocks that fill up `max_variable_byte_sizes`.
+pub fn message_blocks(msg: &[u8]) -> Vec<[u32; 16]> {
+    pad_message(msg)
+        .chunks(BLOCK_BYTE_SIZE)
+        .map(|block| {
+            let mut words = [0u32; 16];
+            for (word, bytes) in words.iter_mut().zip(block.chunks(4)) {
+                *word = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+            }
+            words
+        })
+        .collect()
+}
@@ -0,0 +1,142 @@
+use halo2_base::halo2_proofs::{
+    plonk::{create_proof, verify_proof, Circuit, Error, ProvingKey, VerifyingKey},
+    poly::commitment::{CommitmentScheme, Prover, Verifier},
+    poly::VerificationStrategy,
+    transcript::{
+        Blake2bRead, Blake2bWrite, Challenge255, TranscriptReadBuffer, TranscriptWriterBuffer,
+    },
+};
+use halo2_base::utils::PrimeField;
+use rand::rngs::OsRng;
+
+/// Public inputs of a circuit exposing `digest` byte by byte in its single instance column,
+/// as the tests and `constrain_cells_to_instance` lay it out.
+pub fn digest_instances<F: PrimeField>(digest: &[u8]) -> Vec<F> {
+    digest.iter().map(|byte| F::from(*byte as u64)).collect()
+}
+
+/// Creates a proof with a Blake2b transcript for a circuit with one instance column, using the
+/// commitment scheme `Scheme` and its multiopen prover `P`. The `kzg` and `ipa` modules fix
+/// both for the supported backends.
+pub fn gen_proof<'params, Scheme, P, C>(
+    params: &'params Scheme::ParamsProver,
+    pk: &ProvingKey<Scheme::Curve>,
+    circuit: C,
+    instances: &[Scheme::Scalar],
+) -> Result<Vec<u8>, Error>
+where
+    Scheme: CommitmentScheme,
+    P: Prover<'params, Scheme>,
+    C: Circuit<Scheme::Scalar>,
+{
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof::<Scheme, P, _, _, _, _>(
+        params,
+        pk,
+        &[circuit],
+        &[&[instances]],
+        OsRng,
+        &mut transcript,
+    )?;
+    Ok(transcript.finalize())
+}
+
+/// Verifies a proof made by `gen_proof` against the expected digest bytes as public inputs,
+/// using the multiopen verifier `V` and a single-proof `Strategy` matching the prover.
+pub fn verify_digest_proof<'params, Scheme, V, Strategy>(
+    params: &'params Scheme::ParamsVerifier,
+    vk: &VerifyingKey<Scheme::Curve>,
+    proof: &[u8],
+    expected_digest: &[u8],
+) -> Result<(), Error>
+where
+    Scheme: CommitmentScheme,
+    Scheme::Scalar: PrimeField,
+    V: Verifier<'params, Scheme>,
+    Strategy: VerificationStrategy<'params, Scheme, V, Output = ()>,
+{
+    let instances = digest_instances::<Scheme::Scalar>(expected_digest);
+    let strategy = Strategy::new(params);
+    let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(proof);
+    verify_proof::<Scheme, V, _, _, _>(params, vk, strategy, &[&[&instances]], &mut transcript)
+}
+
+/// KZG over bn256 with the GWC multiopen.
+#[cfg(feature = "kzg")]
+pub mod kzg {
+    use halo2_base::halo2_proofs::{
+        halo2curves::bn256::{Bn256, Fr, G1Affine},
+        plonk::{Circuit, Error, ProvingKey, VerifyingKey},
+        poly::{
+            commitment::ParamsProver,
+            kzg::{
+                commitment::{KZGCommitmentScheme, ParamsKZG},
+                multiopen::{ProverGWC, VerifierGWC},
+                strategy::SingleStrategy,
+            },
+        },
+    };
+
+    pub fn gen_proof<C: Circuit<Fr>>(
+        params: &ParamsKZG<Bn256>,
+        pk: &ProvingKey<G1Affine>,
+        circuit: C,
+        instances: &[Fr],
+    ) -> Result<Vec<u8>, Error> {
+        super::gen_proof::<KZGCommitmentScheme<_>, ProverGWC<_>, _>(params, pk, circuit, instances)
+    }
+
+    pub fn verify_digest_proof(
+        params: &ParamsKZG<Bn256>,
+        vk: &VerifyingKey<G1Affine>,
+        proof: &[u8],
+        expected_digest: &[u8],
+    ) -> Result<(), Error> {
+        super::verify_digest_proof::<KZGCommitmentScheme<_>, VerifierGWC<_>, SingleStrategy<_>>(
+            params.verifier_params(),
+            vk,
+            proof,
+            expected_digest,
+        )
+    }
+}
+
+/// IPA over the Pasta cycle: circuits over `Fp` committed on Vesta (`EqAffine`).
+#[cfg(feature = "ipa")]
+pub mod ipa {
+    use halo2_base::halo2_proofs::{
+        halo2curves::pasta::{EqAffine, Fp},
+        plonk::{Circuit, Error, ProvingKey, VerifyingKey},
+        poly::{
+            commitment::ParamsProver,
+            ipa::{
+                commitment::{IPACommitmentScheme, ParamsIPA},
+                multiopen::{ProverIPA, VerifierIPA},
+                strategy::SingleStrategy,
+            },
+        },
+    };
+
+    pub fn gen_proof<C: Circuit<Fp>>(
+        params: &ParamsIPA<EqAffine>,
+        pk: &ProvingKey<EqAffine>,
+        circuit: C,
+        instances: &[Fp],
+    ) -> Result<Vec<u8>, Error> {
+        super::gen_proof::<IPACommitmentScheme<_>, ProverIPA<_>, _>(params, pk, circuit, instances)
+    }
+
+    pub fn verify_digest_proof(
+        params: &ParamsIPA<EqAffine>,
+        vk: &VerifyingKey<EqAffine>,
+        proof: &[u8],
+        expected_digest: &[u8],
+    ) -> Result<(), Error> {
+        super::verify_digest_proof::<IPACommitmentScheme<_>, VerifierIPA<_>, SingleStrategy<_>>(
+            params.verifier_params(),
+            vk,
+            proof,
+            expected_digest,
+        )
+    }
+}
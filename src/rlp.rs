@@ -0,0 +1,33 @@
+use crate::{num_blocks_for_len, AssignedHashResult, Sha256DynamicConfig};
+use halo2_base::halo2_proofs::plonk::Error;
+use halo2_base::{utils::PrimeField, AssignedValue, Context};
+
+/// Upper bound on the length of the RLP encoding of a string or list with a `payload_len`-byte
+/// payload: one prefix byte, plus the big-endian payload length if it is 56 bytes or more.
+pub fn rlp_encoded_len(payload_len: usize) -> usize {
+    if payload_len < 56 {
+        1 + payload_len
+    } else {
+        let len_of_len = (usize::BITS - payload_len.leading_zeros() + 7) as usize / 8;
+        1 + len_of_len + payload_len
+    }
+}
+
+/// The `max_variable_byte_sizes` entry needed to hash the RLP encoding of a payload of at
+/// most `max_payload_len` bytes with `digest_rlp`.
+pub fn rlp_max_byte_size(max_payload_len: usize) -> usize {
+    num_blocks_for_len(rlp_encoded_len(max_payload_len)) * 64
+}
+
+impl<F: PrimeField> Sha256DynamicConfig<F> {
+    /// Hashes an RLP-encoded structure given as range-checked bytes, e.g. the output of an RLP
+    /// gadget. The encoding itself is not checked. Consumes the next entry of
+    /// `max_variable_byte_sizes`, see `rlp_max_byte_size`.
+    pub fn digest_rlp<'b>(
+        &mut self,
+        ctx: &mut Context<'b, F>,
+        encoded: &[AssignedValue<'b, F>],
+    ) -> Result<AssignedHashResult<'b, F>, Error> {
+        self.digest_assigned_bytes(ctx, encoded)
+    }
+}
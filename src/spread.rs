@@ -1,4 +1,5 @@
 use std::marker::PhantomData;
+use std::sync::Arc;
 
 use crate::{utils::*, SpreadU32};
 use halo2_base::halo2_proofs::halo2curves::FieldExt;
@@ -22,16 +23,99 @@ use hex;
 use itertools::Itertools;
 use num_bigint::BigUint;
 
+/// How a 32-bit word is split into lookup limbs and what each limb spreads to, for debugging.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpreadDecomposition {
+    pub word: u32,
+    pub limb_bits: usize,
+    /// Little-endian limbs of `limb_bits` bits.
+    pub limbs: Vec<u32>,
+    pub spread_limbs: Vec<u64>,
+}
+
+impl SpreadDecomposition {
+    pub fn new(word: u32, limb_bits: usize) -> Self {
+        let limbs = (0..(32 / limb_bits))
+            .map(|idx| (word >> (limb_bits * idx)) & ((1u32 << limb_bits) - 1))
+            .collect_vec();
+        let spread_limbs = limbs.iter().map(|limb| spread_u32(*limb)).collect();
+        Self {
+            word,
+            limb_bits,
+            limbs,
+            spread_limbs,
+        }
+    }
+}
+
+#[cfg(feature = "display")]
+impl std::fmt::Display for SpreadDecomposition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "word {:#x} = {} limbs of {} bits",
+            self.word,
+            self.limbs.len(),
+            self.limb_bits
+        )?;
+        for (idx, (limb, spread)) in self.limbs.iter().zip(self.spread_limbs.iter()).enumerate() {
+            writeln!(f, "limb {}: dense {:#x} -> spread {:#x}", idx, limb, spread)?;
+        }
+        write!(f, "spread {:#x}", spread_u32(self.word))
+    }
+}
+
+/// The `(dense, spread)` rows of the spread lookup table for `num_bits_lookup`-bit limbs.
+/// Computing them dominates table loading, so services building many circuits can compute
+/// the table once and share it through `Sha256DynamicConfig::load_with_spread_table`.
+#[derive(Debug, Clone)]
+pub struct SpreadTable<F: PrimeField> {
+    num_bits_lookup: usize,
+    rows: Arc<Vec<(F, F)>>,
+}
+
+impl<F: PrimeField> SpreadTable<F> {
+    pub fn new(num_bits_lookup: usize) -> Self {
+        let rows = (0..(1u64 << num_bits_lookup))
+            .map(|idx| {
+                let val_dense = F::from(idx);
+                (val_dense, spread_fe(&val_dense))
+            })
+            .collect();
+        Self {
+            num_bits_lookup,
+            rows: Arc::new(rows),
+        }
+    }
+
+    pub fn num_bits_lookup(&self) -> usize {
+        self.num_bits_lookup
+    }
+
+    pub fn rows(&self) -> &[(F, F)] {
+        &self.rows
+    }
+}
+
+/// The columns of the high limb of each lookup in a composite `SpreadConfig`.
+#[derive(Debug, Clone)]
+struct CompositeColumns {
+    denses_hi: Vec<Column<Advice>>,
+    spreads_hi: Vec<Column<Advice>>,
+    table_dense_hi: TableColumn,
+    table_spread_hi: TableColumn,
+}
+
 #[derive(Debug, Clone)]
 pub struct SpreadConfig<F: PrimeField> {
     denses: Vec<Column<Advice>>,
     spreads: Vec<Column<Advice>>,
     table_dense: TableColumn,
     table_spread: TableColumn,
+    composite: Option<CompositeColumns>,
     num_bits_lookup: usize,
     num_advice_columns: usize,
     num_limb_sum: usize,
-    row_offset: usize,
     _f: PhantomData<F>,
 }
 
@@ -41,25 +125,35 @@ impl<F: PrimeField> SpreadConfig<F> {
         num_bits_lookup: usize,
         num_advice_columns: usize,
     ) -> Self {
-        debug_assert_eq!(16 % num_bits_lookup, 0);
         // debug_assert_eq!(16 % (num_bits_lookup * num_advice_columns), 0);
         let denses = (0..num_advice_columns)
-            .map(|_| {
-                let column = meta.advice_column();
-                meta.enable_equality(column);
-                column
-            })
+            .map(|_| meta.advice_column())
             .collect_vec();
         let spreads = (0..num_advice_columns)
-            .map(|_| {
-                let column = meta.advice_column();
-                meta.enable_equality(column);
-                column
-            })
+            .map(|_| meta.advice_column())
             .collect_vec();
+        Self::configure_with_columns(meta, num_bits_lookup, denses, spreads)
+    }
+
+    /// Same as `configure`, but places the dense and spread limbs in the given advice columns.
+    /// The columns must not be used by any other chip.
+    pub fn configure_with_columns(
+        meta: &mut ConstraintSystem<F>,
+        num_bits_lookup: usize,
+        denses: Vec<Column<Advice>>,
+        spreads: Vec<Column<Advice>>,
+    ) -> Self {
+        debug_assert_eq!(16 % num_bits_lookup, 0);
+        assert_eq!(denses.len(), spreads.len());
+        let num_advice_columns = denses.len();
+        for column in denses.iter().chain(spreads.iter()) {
+            meta.enable_equality(*column);
+        }
 
         let table_dense = meta.lookup_table_column();
         let table_spread = meta.lookup_table_column();
+        // unselected, so every row of each (dense, spread) pair, including unassigned rows which
+        // read as (0, 0), must be an entry of the table.
         for (idx, (dense, spread)) in denses.iter().zip(spreads.iter()).enumerate() {
             meta.lookup("spread lookup", |meta| {
                 let dense = meta.query_advice(*dense, Rotation::cur());
@@ -72,14 +166,99 @@ impl<F: PrimeField> SpreadConfig<F> {
             spreads,
             table_dense,
             table_spread,
+            composite: None,
             num_bits_lookup,
             num_advice_columns,
             num_limb_sum: 0,
-            row_offset: 0,
             _f: PhantomData,
         }
     }
 
+    /// Same as `configure` with 4-bit limbs, but packs two limbs into each lookup against a
+    /// combined table of all 256 `(lo, hi)` limb pairs, so a 16-bit word takes 2 lookups
+    /// instead of 4.
+    pub fn configure_composite(meta: &mut ConstraintSystem<F>, num_advice_columns: usize) -> Self {
+        let mut advice_columns = || {
+            (0..num_advice_columns)
+                .map(|_| {
+                    let column = meta.advice_column();
+                    meta.enable_equality(column);
+                    column
+                })
+                .collect_vec()
+        };
+        let denses = advice_columns();
+        let spreads = advice_columns();
+        let denses_hi = advice_columns();
+        let spreads_hi = advice_columns();
+
+        let table_dense = meta.lookup_table_column();
+        let table_spread = meta.lookup_table_column();
+        let table_dense_hi = meta.lookup_table_column();
+        let table_spread_hi = meta.lookup_table_column();
+        // unselected as in `configure_with_columns`; unassigned rows read as the pair (0, 0).
+        for idx in 0..num_advice_columns {
+            let columns = [
+                (denses[idx], table_dense),
+                (denses_hi[idx], table_dense_hi),
+                (spreads[idx], table_spread),
+                (spreads_hi[idx], table_spread_hi),
+            ];
+            meta.lookup("composite spread lookup", |meta| {
+                columns
+                    .iter()
+                    .map(|(column, table)| (meta.query_advice(*column, Rotation::cur()), *table))
+                    .collect()
+            });
+        }
+        Self {
+            denses,
+            spreads,
+            table_dense,
+            table_spread,
+            composite: Some(CompositeColumns {
+                denses_hi,
+                spreads_hi,
+                table_dense_hi,
+                table_spread_hi,
+            }),
+            num_bits_lookup: 4,
+            num_advice_columns,
+            num_limb_sum: 0,
+            _f: PhantomData,
+        }
+    }
+
+    /// Whether each lookup packs two limbs, see `configure_composite`.
+    pub fn is_composite(&self) -> bool {
+        self.composite.is_some()
+    }
+
+    /// The number of rows of the lookup table.
+    pub fn table_size(&self) -> usize {
+        if self.is_composite() {
+            1 << (2 * self.num_bits_lookup)
+        } else {
+            1 << self.num_bits_lookup
+        }
+    }
+
+    pub fn decomposition(&self, word: u32) -> SpreadDecomposition {
+        SpreadDecomposition::new(word, self.num_bits_lookup)
+    }
+
+    pub fn dense_columns(&self) -> &[Column<Advice>] {
+        &self.denses
+    }
+
+    pub fn spread_columns(&self) -> &[Column<Advice>] {
+        &self.spreads
+    }
+
+    pub fn table_columns(&self) -> (TableColumn, TableColumn) {
+        (self.table_dense, self.table_spread)
+    }
+
     pub fn spread<'v: 'a, 'a>(
         &mut self,
         ctx: &mut Context<'v, F>,
@@ -114,11 +293,22 @@ impl<F: PrimeField> SpreadConfig<F> {
                 QuantumCell::Existing(&dense),
             );
         }
+        let mut spread_limbs = vec![];
+        if self.is_composite() {
+            for pair in assigned_limbs.chunks(2) {
+                let (spread_lo, spread_hi) =
+                    self.spread_limb_pair(ctx, &gate, &pair[0], &pair[1])?;
+                spread_limbs.push(spread_lo);
+                spread_limbs.push(spread_hi);
+            }
+        } else {
+            for limb in assigned_limbs.iter() {
+                spread_limbs.push(self.spread_limb(ctx, &gate, limb)?);
+            }
+        }
         let mut assigned_spread = gate.load_zero(ctx);
         // println!("dense: {:?}", dense.value());
-        for (idx, limb) in assigned_limbs.iter().enumerate() {
-            // println!("idx {}, limb {:?}", idx, limb.value());
-            let spread_limb = self.spread_limb(ctx, &gate, limb)?;
+        for (idx, spread_limb) in spread_limbs.iter().enumerate() {
             assigned_spread = gate.mul_add(
                 ctx,
                 QuantumCell::Existing(&spread_limb),
@@ -170,28 +360,56 @@ impl<F: PrimeField> SpreadConfig<F> {
     }
 
     pub fn load(&self, layouter: &mut impl Layouter<F>) -> Result<(), Error> {
+        self.load_with_table(layouter, &SpreadTable::new(self.num_bits_lookup))
+    }
+
+    /// Same as `load`, but assigns the rows of a precomputed `table`.
+    pub fn load_with_table(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        table: &SpreadTable<F>,
+    ) -> Result<(), Error> {
+        assert_eq!(table.num_bits_lookup(), self.num_bits_lookup);
+        if let Some(composite) = self.composite.as_ref() {
+            return layouter.assign_table(
+                || "composite spread table",
+                |mut table_region| {
+                    let pairs = table.rows().iter().cartesian_product(table.rows().iter());
+                    for (idx, ((dense_lo, spread_lo), (dense_hi, spread_hi))) in pairs.enumerate() {
+                        let cells = [
+                            (self.table_dense, dense_lo),
+                            (composite.table_dense_hi, dense_hi),
+                            (self.table_spread, spread_lo),
+                            (composite.table_spread_hi, spread_hi),
+                        ];
+                        for (column, value) in cells.iter() {
+                            table_region.assign_cell(
+                                || format!("composite spread table at {}", idx),
+                                *column,
+                                idx,
+                                || Value::known(**value),
+                            )?;
+                        }
+                    }
+                    Ok(())
+                },
+            );
+        }
         layouter.assign_table(
             || "spread table",
-            |mut table| {
-                for idx in 0..(1usize << self.num_bits_lookup) {
-                    let val_dense = F::from(idx as u64);
-                    let val_bits = fe_to_bits_le(&val_dense, 32);
-                    let mut spread_bits = vec![false; val_bits.len() * 2];
-                    for i in 0..val_bits.len() {
-                        spread_bits[2 * i] = val_bits[i];
-                    }
-                    let val_spread: F = bits_le_to_fe(&spread_bits);
-                    table.assign_cell(
+            |mut table_region| {
+                for (idx, (val_dense, val_spread)) in table.rows().iter().enumerate() {
+                    table_region.assign_cell(
                         || format!("table_dense at {}", idx),
                         self.table_dense,
                         idx,
-                        || Value::known(val_dense),
+                        || Value::known(*val_dense),
                     )?;
-                    table.assign_cell(
+                    table_region.assign_cell(
                         || format!("table_spread at {}", idx),
                         self.table_spread,
                         idx,
-                        || Value::known(val_spread),
+                        || Value::known(*val_spread),
                     )?;
                 }
                 Ok(())
@@ -200,42 +418,106 @@ impl<F: PrimeField> SpreadConfig<F> {
         Ok(())
     }
 
+    /// Restarts limb assignment at the first row of the first column, e.g. for a new region.
+    pub fn reset(&mut self) {
+        self.num_limb_sum = 0;
+    }
+
+    /// The lookups made so far. A composite lookup covers two limbs.
+    pub fn num_lookups(&self) -> usize {
+        self.num_limb_sum
+    }
+
     fn spread_limb<'v: 'a, 'a>(
         &mut self,
         ctx: &mut Context<'v, F>,
         gate: &FlexGateConfig<F>,
         limb: &AssignedValue<F>,
     ) -> Result<AssignedValue<'a, F>, Error> {
-        let column_idx = self.num_limb_sum % self.num_advice_columns;
-        let assigned_dense_cell = ctx.region.assign_advice(
-            || format!("dense at offset {}", self.row_offset),
-            self.denses[column_idx],
-            self.row_offset,
-            || limb.value,
-        )?;
-        ctx.region
-            .constrain_equal(assigned_dense_cell.cell(), limb.cell())?;
-        let spread_value: Value<F> = limb.value().map(|val| {
-            let val_bits = fe_to_bits_le(val, 32);
-            let mut spread_bits = vec![false; val_bits.len() * 2];
-            for i in 0..val_bits.len() {
-                spread_bits[2 * i] = val_bits[i];
-            }
-            bits_le_to_fe(&spread_bits)
-        });
-        let assigned_spread_cell = ctx.region.assign_advice(
-            || format!("spread at offset {}", self.row_offset),
-            self.spreads[column_idx],
-            self.row_offset,
-            || spread_value,
-        )?;
-        let assigned_spread_value = gate.load_witness(ctx, spread_value);
-        ctx.region
-            .constrain_equal(assigned_spread_cell.cell(), assigned_spread_value.cell())?;
+        let (column_idx, row_offset) = limb_position(self.num_limb_sum, self.num_advice_columns);
+        let (dense, spread) = (self.denses[column_idx], self.spreads[column_idx]);
+        let assigned_spread_value = assign_limb(ctx, gate, dense, spread, row_offset, limb)?;
         self.num_limb_sum += 1;
-        if column_idx == self.num_advice_columns - 1 {
-            self.row_offset += 1;
-        }
         Ok(assigned_spread_value)
     }
+
+    // Assigns `lo` and `hi` to the same row of a composite config, so one lookup covers both.
+    fn spread_limb_pair<'v: 'a, 'a>(
+        &mut self,
+        ctx: &mut Context<'v, F>,
+        gate: &FlexGateConfig<F>,
+        lo: &AssignedValue<F>,
+        hi: &AssignedValue<F>,
+    ) -> Result<(AssignedValue<'a, F>, AssignedValue<'a, F>), Error> {
+        let composite = self.composite.as_ref().unwrap();
+        let (column_idx, row_offset) = limb_position(self.num_limb_sum, self.num_advice_columns);
+        let (dense_lo, spread_lo) = (self.denses[column_idx], self.spreads[column_idx]);
+        let (dense_hi, spread_hi) = (
+            composite.denses_hi[column_idx],
+            composite.spreads_hi[column_idx],
+        );
+        let assigned_spread_lo = assign_limb(ctx, gate, dense_lo, spread_lo, row_offset, lo)?;
+        let assigned_spread_hi = assign_limb(ctx, gate, dense_hi, spread_hi, row_offset, hi)?;
+        self.num_limb_sum += 1;
+        Ok((assigned_spread_lo, assigned_spread_hi))
+    }
+
+    /// Recomputes the spread form of `word` limb by limb, exactly as the lookup table is filled,
+    /// and compares it with a reference bit interleaving.
+    #[cfg(feature = "spread-self-test")]
+    pub fn self_check(&self, word: u32) -> bool {
+        let limb_bits = self.num_bits_lookup;
+        let limbs = decompose(&F::from(word as u64), 32 / limb_bits, limb_bits);
+        let mut spread = F::zero();
+        for (idx, limb) in limbs.iter().enumerate() {
+            spread += spread_fe(limb) * F::from(1u64 << (2 * limb_bits * idx));
+        }
+        spread == F::from(spread_u32(word))
+    }
+}
+
+// Copies `limb` into `dense` and its spread value into `spread` at `row_offset`, returning the
+// spread value as a gate cell.
+fn assign_limb<'v: 'a, 'a, F: PrimeField>(
+    ctx: &mut Context<'v, F>,
+    gate: &FlexGateConfig<F>,
+    dense: Column<Advice>,
+    spread: Column<Advice>,
+    row_offset: usize,
+    limb: &AssignedValue<F>,
+) -> Result<AssignedValue<'a, F>, Error> {
+    let assigned_dense_cell = ctx.region.assign_advice(
+        || format!("dense at offset {}", row_offset),
+        dense,
+        row_offset,
+        || limb.value,
+    )?;
+    ctx.region
+        .constrain_equal(assigned_dense_cell.cell(), limb.cell())?;
+    let spread_value: Value<F> = limb.value().map(|val| spread_fe(val));
+    let assigned_spread_cell = ctx.region.assign_advice(
+        || format!("spread at offset {}", row_offset),
+        spread,
+        row_offset,
+        || spread_value,
+    )?;
+    let assigned_spread_value = gate.load_witness(ctx, spread_value);
+    ctx.region
+        .constrain_equal(assigned_spread_cell.cell(), assigned_spread_value.cell())?;
+    Ok(assigned_spread_value)
+}
+
+/// The `(column, row)` of the `limb_idx`-th spread limb: limbs fill each row across all
+/// `num_columns` columns before moving to the next row.
+pub(crate) fn limb_position(limb_idx: usize, num_columns: usize) -> (usize, usize) {
+    (limb_idx % num_columns, limb_idx / num_columns)
+}
+
+fn spread_fe<F: PrimeField>(val: &F) -> F {
+    let val_bits = fe_to_bits_le(val, 32);
+    let mut spread_bits = vec![false; val_bits.len() * 2];
+    for i in 0..val_bits.len() {
+        spread_bits[2 * i] = val_bits[i];
+    }
+    bits_le_to_fe(&spread_bits)
 }
@@ -30,3 +30,9 @@ pub fn bits_le_to_fe<F: PrimeField>(bits: &[bool]) -> F {
         .collect_vec();
     biguint_to_fe(&BigUint::from_bytes_le(&bytes))
 }
+
+pub fn spread_u32(word: u32) -> u64 {
+    (0..32).fold(0u64, |acc, idx| {
+        acc | (((word >> idx) & 1) as u64) << (2 * idx)
+    })
+}